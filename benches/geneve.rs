@@ -0,0 +1,175 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use geneve_rs::geneve::{GenevePacket, Header, HeaderBuilder, TunnelOption, MIN_GENEVE_HDR};
+
+const ENCODED: [u8; 24] = [
+    0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00, 0x00,
+    0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+];
+
+fn eight_options() -> Header<'static> {
+    let mut builder = HeaderBuilder::new().protocol(0x6558).vni(0x00aaaaee);
+    for i in 0..8 {
+        builder = builder.add_option(TunnelOption {
+            option_class: 0x0108,
+            option_type: i,
+            c_flag: false,
+            data: Some(&[0x00, 0x01, 0x02, 0x03, 0x04, 0x05]),
+        });
+    }
+    builder.build().unwrap()
+}
+
+// Eight options of 4 data bytes each, 64 bytes of options in total
+fn eight_options_encoded() -> Vec<u8> {
+    let mut builder = HeaderBuilder::new().protocol(0x6558).vni(0x00aaaaee);
+    for i in 0..8 {
+        builder = builder.add_option(TunnelOption {
+            option_class: 0x0108,
+            option_type: i,
+            c_flag: false,
+            data: Some(&[0x00, 0x01, 0x02, 0x03]),
+        });
+    }
+    let mut buffer = vec![];
+    builder.build().unwrap().marshal(&mut buffer).unwrap();
+    buffer
+}
+
+// 63 data-less options, the largest options length the header can declare
+fn max_options_encoded() -> Vec<u8> {
+    let mut buffer = vec![0x3f, 0x00, 0x65, 0x58, 0x00, 0xaa, 0xaa, 0x00];
+    for i in 0..63 {
+        buffer.extend_from_slice(&[0x01, 0x08, i, 0x00]);
+    }
+    buffer
+}
+
+fn options_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("options_parse");
+    group.bench_function("unmarshal_vec", |b| {
+        b.iter(|| {
+            let (hdr, _) = Header::unmarshal(black_box(&ENCODED)).unwrap();
+            hdr.options
+                .unwrap()
+                .into_iter()
+                .find(|i| i.option_type == 0x0b)
+        })
+    });
+    group.bench_function("options_iter", |b| {
+        b.iter(|| {
+            Header::options_iter(black_box(&ENCODED))
+                .unwrap()
+                .find(|i: &TunnelOption| i.option_type == 0x0b)
+        })
+    });
+    // Searching for the last of eight options: eager parse allocates, the iterator does not
+    let encoded = eight_options_encoded();
+    group.bench_function("unmarshal_find_option", |b| {
+        b.iter(|| {
+            let (hdr, _) = Header::unmarshal(black_box(&encoded)).unwrap();
+            hdr.find_option(0x0108, 0x07).map(|i| i.data)
+        })
+    });
+    group.bench_function("options_iter_find", |b| {
+        b.iter(|| {
+            Header::options_iter(black_box(&encoded))
+                .unwrap()
+                .find(|i| i.option_class == 0x0108 && i.option_type == 0x07)
+                .map(|i| i.data)
+        })
+    });
+    // Options Vec is sized once up front instead of regrowing across 63 pushes
+    let max_options = max_options_encoded();
+    group.bench_function("unmarshal_max_options", |b| {
+        b.iter(|| Header::unmarshal(black_box(&max_options)).unwrap())
+    });
+    // Same 63 options into a caller-provided slice, isolating the per-option loop cost
+    group.bench_function("unmarshal_into_max_options", |b| {
+        let mut opts = vec![TunnelOption::new(0, 0, false, None).unwrap(); 63];
+        b.iter(|| {
+            Header::unmarshal_into(black_box(&max_options), &mut opts)
+                .unwrap()
+                .2
+        })
+    });
+    group.bench_function("count_options_max_options", |b| {
+        b.iter(|| Header::count_options(black_box(&max_options)))
+    });
+    group.finish();
+}
+
+fn header_marshal(c: &mut Criterion) {
+    let hdr = eight_options();
+    let fixed_hdr = HeaderBuilder::new()
+        .protocol(0x6558)
+        .vni(0x00aaaaee)
+        .build()
+        .unwrap();
+    let mut group = c.benchmark_group("header_marshal");
+    // Previous strategy: options into a temporary Vec, then copied after the fixed header
+    group.bench_function("intermediate_buffer", |b| {
+        b.iter(|| {
+            let hdr = black_box(&hdr);
+            let mut opt_buffer = vec![];
+            for i in hdr.options.iter().flatten() {
+                i.marshal(&mut opt_buffer).unwrap();
+            }
+            let mut fixed = [0; MIN_GENEVE_HDR];
+            fixed_hdr.marshal_to_slice(&mut fixed).unwrap();
+            fixed[0] |= (opt_buffer.len() / 4) as u8;
+            let mut buffer = vec![];
+            buffer.extend_from_slice(&fixed);
+            buffer.extend_from_slice(&opt_buffer);
+            buffer
+        })
+    });
+    group.bench_function("marshal", |b| {
+        b.iter(|| {
+            let mut buffer = vec![];
+            black_box(&hdr).marshal(&mut buffer).unwrap();
+            buffer
+        })
+    });
+    group.bench_function("marshal_to_slice", |b| {
+        let mut buffer = [0; 128];
+        b.iter(|| black_box(&hdr).marshal_to_slice(&mut buffer).unwrap())
+    });
+    // Previous strategy: fixed header written field by field with byte-slice copies
+    group.bench_function("fixed_header_bytewise", |b| {
+        let mut buffer = [0; MIN_GENEVE_HDR];
+        b.iter(|| {
+            let hdr = black_box(&fixed_hdr);
+            buffer[0] = (hdr.version << 6) | (((hdr.options_total_len() / 4) as u8) & 0x3f);
+            buffer[1] = ((hdr.control_flag as u8) << 7)
+                | ((hdr.critical_flag as u8) << 6)
+                | (hdr.reserved1 & 0x3f);
+            buffer[2..4].copy_from_slice(&hdr.protocol.to_be_bytes());
+            buffer[4..7].copy_from_slice(&hdr.vni.to_be_bytes()[1..]);
+            buffer[7] = hdr.reserved2;
+            buffer
+        })
+    });
+    group.bench_function("fixed_header_marshal_to_slice", |b| {
+        let mut buffer = [0; MIN_GENEVE_HDR];
+        b.iter(|| black_box(&fixed_hdr).marshal_to_slice(&mut buffer).unwrap())
+    });
+    group.finish();
+}
+
+fn payload_offset(c: &mut Criterion) {
+    let mut group = c.benchmark_group("payload_offset");
+    group.bench_function("unmarshal", |b| {
+        b.iter(|| {
+            GenevePacket::unmarshal(black_box(&ENCODED))
+                .unwrap()
+                .payload_offset()
+        })
+    });
+    group.bench_function("peek", |b| {
+        b.iter(|| GenevePacket::peek(black_box(&ENCODED)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, options_parse, header_marshal, payload_offset);
+criterion_main!(benches);
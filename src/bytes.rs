@@ -0,0 +1,42 @@
+use crate::geneve::{GeneveErr, GenevePacket, Header};
+use bytes::BytesMut;
+
+impl Header<'_> {
+    // Appends the same bytes as `marshal`, written in place after reserving `header_len()`.
+    // `buf` is left unchanged on error
+    pub fn marshal_to_bytes(&self, buf: &mut BytesMut) -> Result<(), GeneveErr> {
+        let start = buf.len();
+        buf.resize(start + self.header_len(), 0);
+        if let Err(e) = self.marshal_to_slice(&mut buf[start..]) {
+            buf.truncate(start);
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+impl GenevePacket<'_> {
+    // Appends the same bytes as `marshal`, reserving `total_len()` up front
+    pub fn marshal_to_bytes(&self, buf: &mut BytesMut) -> Result<(), GeneveErr> {
+        buf.reserve(self.total_len());
+        self.hdr.marshal_to_bytes(buf)?;
+        buf.extend_from_slice(self.inner_payload());
+        Ok(())
+    }
+}
+
+#[test]
+fn geneve_bytes_marshal() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let packet = GenevePacket::unmarshal(&encoded_payload).unwrap();
+    let mut buf = BytesMut::new();
+    packet.marshal_to_bytes(&mut buf).unwrap();
+    assert_eq!(buf.freeze(), encoded_payload[..]);
+    let mut buf = BytesMut::from(&[0xee][..]);
+    packet.hdr.marshal_to_bytes(&mut buf).unwrap();
+    assert_eq!(buf[0], 0xee);
+    assert_eq!(buf[1..], encoded_payload[..24]);
+}
@@ -0,0 +1,119 @@
+use crate::geneve::{GeneveErr, GenevePacket, GenevePacketBuf};
+use bytes::BytesMut;
+use tokio_util::codec::Decoder;
+
+// Datagram-only decoder: each call to `decode_eof` takes the whole buffer as one Geneve
+// packet, which is how `UdpFramed` hands over each UDP datagram. Geneve carries no payload
+// length, so packets cannot be split out of a byte stream. `decode` only checks the header
+// seen so far and never yields, a stream through `FramedRead` is decoded as a single
+// packet at EOF. Frame streams first (e.g. with `LengthDelimitedCodec`) to carry several.
+#[derive(Debug, Default)]
+pub struct GeneveDecoder;
+
+impl GeneveDecoder {
+    pub fn new() -> Self {
+        GeneveDecoder
+    }
+}
+
+impl Decoder for GeneveDecoder {
+    type Item = GenevePacketBuf;
+    type Error = GeneveErr;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match GenevePacket::peek(src) {
+            // The end of the datagram is unknown until `decode_eof`, even with a complete header
+            Ok(_) | Err(GeneveErr::BufferTooShort { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.is_empty() {
+            return Ok(None);
+        }
+        let frame = src.split_to(src.len());
+        Ok(Some(GenevePacket::unmarshal(&frame)?.to_buf()))
+    }
+}
+
+#[test]
+fn geneve_decoder_partial_chunks() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let mut decoder = GeneveDecoder::new();
+    let mut src = BytesMut::new();
+    src.extend_from_slice(&encoded_payload[..12]);
+    assert!(decoder.decode(&mut src).unwrap().is_none());
+    assert_eq!(src.len(), 12);
+    // A complete header is not enough, more of the inner payload may still arrive
+    src.extend_from_slice(&encoded_payload[12..26]);
+    assert!(decoder.decode(&mut src).unwrap().is_none());
+    assert_eq!(src.len(), 26);
+    src.extend_from_slice(&encoded_payload[26..]);
+    let packet = decoder.decode_eof(&mut src).unwrap().unwrap();
+    assert!(src.is_empty());
+    assert!(decoder.decode_eof(&mut src).unwrap().is_none());
+    assert_eq!(packet.hdr.vni, 0x00aaaaee);
+    assert_eq!(packet.hdr.options.as_ref().map(|i| i.len()), Some(2));
+    assert_eq!(packet.payload, [0xde, 0xad, 0xbe, 0xef, 0x00, 0x00]);
+    let mut buffer: Vec<u8> = vec![];
+    packet.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded_payload);
+}
+
+#[test]
+fn geneve_decoder_two_frames() {
+    let first: [u8; 12] = [
+        0x00, 0x00, 0x86, 0xdd, 0x00, 0x00, 0x01, 0x00, 0x60, 0x00, 0x00, 0x00,
+    ];
+    let second: [u8; 10] = [0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x00, 0x45, 0x00];
+    let mut decoder = GeneveDecoder::new();
+    let mut src = BytesMut::new();
+    src.extend_from_slice(&first);
+    src.extend_from_slice(&second);
+    // Nothing is split off a stream, both frames stay buffered
+    assert!(decoder.decode(&mut src).unwrap().is_none());
+    assert_eq!(src.len(), 22);
+    // At the end of a datagram every byte after the header is the inner payload
+    let packet = decoder.decode_eof(&mut src).unwrap().unwrap();
+    assert_eq!(packet.hdr.vni, 1);
+    assert_eq!(packet.payload[..4], first[8..]);
+    assert_eq!(packet.payload[4..], second);
+    // One datagram per `decode_eof`, as `UdpFramed` delivers them
+    let mut decoded = vec![];
+    for datagram in [&first[..], &second[..]] {
+        src.extend_from_slice(datagram);
+        while let Some(i) = decoder.decode_eof(&mut src).unwrap() {
+            decoded.push(i);
+        }
+    }
+    assert_eq!(decoded.len(), 2);
+    assert_eq!(decoded[0].hdr.vni, 1);
+    assert_eq!(decoded[0].payload, first[8..]);
+    assert_eq!(decoded[1].hdr.vni, 2);
+    assert_eq!(decoded[1].payload, second[8..]);
+}
+
+#[test]
+fn geneve_decoder_truncated_datagram() {
+    let mut decoder = GeneveDecoder::new();
+    let mut src = BytesMut::from(&[0x01, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00][..]);
+    assert!(decoder.decode(&mut src).unwrap().is_none());
+    assert!(matches!(
+        decoder.decode_eof(&mut src),
+        Err(GeneveErr::BufferTooShort { needed: 12, got: 8 })
+    ));
+}
+
+#[test]
+fn geneve_decoder_unsupported_version() {
+    let mut decoder = GeneveDecoder::new();
+    let mut src = BytesMut::from(&[0x40, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00][..]);
+    assert!(matches!(
+        decoder.decode(&mut src),
+        Err(GeneveErr::UnsupportedVersion(1))
+    ));
+}
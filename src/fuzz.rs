@@ -0,0 +1,75 @@
+use crate::geneve::{
+    Header, TunnelOption, MAX_DATA_SIZE, MAX_OPTIONS_LEN, MAX_OPTION_TYPE, MAX_VNI,
+};
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+// Only structurally valid values are generated, so every one of them marshals
+impl<'a> Arbitrary<'a> for TunnelOption<'a> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let option_class = u.arbitrary()?;
+        let option_type = u.int_in_range(0..=MAX_OPTION_TYPE)?;
+        let c_flag = u.arbitrary()?;
+        let data = match u.int_in_range(0..=MAX_DATA_SIZE)? {
+            0 => None,
+            i => Some(u.bytes(i)?),
+        };
+        Ok(TunnelOption {
+            option_class,
+            option_type,
+            c_flag,
+            data,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Header<'a> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut hdr = Header {
+            version: 0,
+            control_flag: u.arbitrary()?,
+            critical_flag: u.arbitrary()?,
+            reserved1: u.int_in_range(0..=0x3f)?,
+            protocol: u.arbitrary()?,
+            vni: u.int_in_range(0..=MAX_VNI)?,
+            reserved2: u.arbitrary()?,
+            options: None,
+        };
+        let mut options = Vec::new();
+        let mut len = 0;
+        for i in u.arbitrary_iter::<TunnelOption>()? {
+            let option = i?;
+            // Stop before the options outgrow the 6-bit options length field
+            if len + option.total_len() > MAX_OPTIONS_LEN {
+                break;
+            }
+            len += option.total_len();
+            options.push(option);
+        }
+        if !options.is_empty() {
+            hdr.options = Some(options);
+        }
+        Ok(hdr)
+    }
+}
+
+#[test]
+fn geneve_arbitrary_header() {
+    let seed: Vec<u8> = (0..=255).cycle().take(4096).collect();
+    for i in 0..8 {
+        let mut u = Unstructured::new(&seed[i * 37..]);
+        let hdr = Header::arbitrary(&mut u).unwrap();
+        assert!(hdr.vni <= MAX_VNI);
+        assert!(hdr.options_total_len() <= MAX_OPTIONS_LEN);
+        for i in hdr.options() {
+            assert!(i.option_type <= MAX_OPTION_TYPE);
+            assert!(i.data_len() <= MAX_DATA_SIZE);
+        }
+        let mut buffer: Vec<u8> = vec![];
+        hdr.marshal(&mut buffer).unwrap();
+        let (decoded, cursor) = Header::unmarshal(&buffer).unwrap();
+        assert_eq!(cursor, buffer.len());
+        let mut encoded: Vec<u8> = vec![];
+        decoded.marshal(&mut encoded).unwrap();
+        assert_eq!(encoded, buffer);
+    }
+}
@@ -1,12 +1,89 @@
+use crate::wire::{read_u16_be, read_u24_be, write_u16_be, write_u24_be};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use core::fmt;
+
 pub const MIN_GENEVE_HDR: usize = 8;
+pub const MIN_OPT_SIZE: usize = 4;
+// Option length field is 5 bits in 4-byte words: 31 * 4 = 124 bytes
+pub const MAX_DATA_SIZE: usize = 124;
+// Options length field is 6 bits in 4-byte words: 63 * 4 = 252 bytes
+pub const MAX_OPTIONS_LEN: usize = 252;
+// Fixed header plus the largest options region, enough for any `marshal_to_slice` of a header
+pub const MAX_HEADER_LEN: usize = MIN_GENEVE_HDR + MAX_OPTIONS_LEN;
+// Option type is 7 bits, the high bit of its byte is the critical flag
+pub const MAX_OPTION_TYPE: u8 = 0x7f;
+// VNI field is 24 bits
+pub const MAX_VNI: u32 = 0x00ffffff;
+
+// Whether a buffer of `buffer_len` bytes can hold the fixed header
+pub const fn is_minimum_length(buffer_len: usize) -> bool {
+    buffer_len >= MIN_GENEVE_HDR
+}
 
-// Enum for errors
+// Enum for errors, new variants may be added without a breaking release
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum GeneveErr {
-    NotGeneve,
+    // Input ends before a complete option
     InvalidLength,
+    // Input ends before the header or its declared options. `needed` and `got` are byte counts
+    BufferTooShort {
+        needed: usize,
+        got: usize,
+    },
+    // Output slice cannot hold the encoded bytes. `needed` and `got` are byte counts
+    OutputTooSmall {
+        needed: usize,
+        got: usize,
+    },
+    // Options slice passed to `unmarshal_into` cannot hold every option. `needed` and `got`
+    // are option counts
+    OptionSlotsTooFew {
+        needed: usize,
+        got: usize,
+    },
+    // Option data exceeds MAX_DATA_SIZE
+    OptionTooLong,
+    // Options exceed MAX_OPTIONS_LEN
+    OptionsTooLong,
+    // Option data is not a multiple of 4 bytes where no padding is added
+    UnalignedData,
+    // Option type does not fit 7 bits, the high bit is the critical flag
+    InvalidOptionType,
+    // Protocol type 0x0000 is reserved
+    InvalidProtocol,
+    // Reserved header bits must be zero under strict parsing
+    ReservedBitsSet,
+    InvalidVni,
+    UnsupportedVersion(u8),
+    // Options region ends inside an option, its header or declared data is cut off
+    TruncatedOption,
+    UnknownCriticalOption {
+        class: u16,
+        option_type: u8,
+    },
+    // Every unknown critical (class, type) pair, in wire order
+    #[cfg(feature = "alloc")]
+    UnknownCriticalOptions(Vec<(u16, u8)>),
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for GeneveErr {
+    fn from(err: std::io::Error) -> Self {
+        GeneveErr::Io(err)
+    }
 }
 
+#[cfg(feature = "std")]
+impl From<GeneveErr> for std::io::Error {
+    fn from(err: GeneveErr) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{:?}", err))
+    }
+}
 
 //   Geneve Packet:
 //      +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
@@ -16,63 +93,340 @@ pub enum GeneveErr {
 //      +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 
 // Implementation of GenevePacket
-#[derive(Debug, PartialEq)]
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GenevePacket<'a> {
-    pub hdr: Header,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub hdr: Header<'a>,
     offset: usize,
+    // Whole packet when parsed (the whole frame for `unmarshal_at`), only the inner frame when
+    // built with `from_parts`.
+    // Use `inner_payload()` to get the encapsulated frame in either case
     pub payload: &'a [u8],
 }
 
+#[cfg(feature = "alloc")]
 impl<'a> GenevePacket<'a> {
-    pub fn new(packet: &'a [u8]) -> Option<GenevePacket> {
-        if let Some((i, k)) = Header::unmarshal(packet) {
-            Some(GenevePacket {
-                hdr: i,
-                offset: k,
-                payload: packet,
-            })
-        } else {
-            None
+    // `unmarshal` without the failure reason
+    pub fn new(packet: &'a [u8]) -> Option<GenevePacket<'a>> {
+        GenevePacket::try_from(packet).ok()
+    }
+    // Packet from a header and the encapsulated frame alone, `payload_offset()` is 0
+    pub fn from_parts(hdr: Header<'a>, inner_payload: &'a [u8]) -> Self {
+        GenevePacket {
+            hdr,
+            offset: 0,
+            payload: inner_payload,
         }
     }
-    pub fn marshal(&self, buffer: &mut Vec<u8>) {
-        let mut hdr_buffer = vec![];
-        self.hdr.marshal(&mut hdr_buffer);
-        buffer.extend_from_slice(&hdr_buffer[..]);
-        buffer.extend_from_slice(&self.payload[self.offset..]);
-    }
-    pub fn unmarshal (buffer: &'a [u8]) -> Result<Self, GeneveErr> {
-        if buffer.len() >= MIN_GENEVE_HDR {
-            if let Some((i, cur)) = Header::unmarshal(buffer) {
-                let pckt = GenevePacket {
-                    hdr: i,
-                    offset: cur,
-                    payload: buffer,
-                };
-                Ok(pckt)
-            } else {
-                Err(GeneveErr::NotGeneve)
+    // Offset of the inner payload into `payload`, equal to `hdr.header_len()` for well-formed
+    // packets and relative to the start of the frame for `unmarshal_at`
+    pub fn payload_offset(&self) -> usize {
+        self.offset
+    }
+    // RFC 8926: packets carrying a critical option the endpoint does not recognize must be dropped
+    pub fn validate_critical(&self, known: &[(u16, u8)]) -> Result<(), GeneveErr> {
+        for i in self.hdr.options.iter().flatten() {
+            if i.c_flag && !known.contains(&(i.option_class, i.option_type)) {
+                return Err(GeneveErr::UnknownCriticalOption {
+                    class: i.option_class,
+                    option_type: i.option_type,
+                });
             }
-        } else {
-            Err(GeneveErr::InvalidLength)
         }
+        Ok(())
+    }
+    // Same rule as `validate_critical`, reporting all unknown critical options at once
+    pub fn validate_against(&self, registry: &OptionRegistry) -> Result<(), GeneveErr> {
+        let unknown: Vec<(u16, u8)> = self
+            .hdr
+            .options()
+            .iter()
+            .filter(|i| i.c_flag && !registry.contains(i.option_class, i.option_type))
+            .map(|i| (i.option_class, i.option_type))
+            .collect();
+        match unknown.is_empty() {
+            true => Ok(()),
+            false => Err(GeneveErr::UnknownCriticalOptions(unknown)),
+        }
+    }
+    // Options of the header, empty when it carries none
+    pub fn options(&self) -> &[TunnelOption<'a>] {
+        self.hdr.options()
+    }
+    // O bit, OAM packets are usually diverted to a slow path
+    pub fn is_oam(&self) -> bool {
+        self.hdr.oam()
+    }
+    pub fn is_control(&self) -> bool {
+        self.is_oam()
+    }
+    // Appends an option, the options length is derived when marshalling. The packet borrows
+    // its buffer, so marshal it into a new buffer afterwards. Use `hdr.normalize()` to set
+    // the C bit for a critical option
+    pub fn insert_option(&mut self, option: TunnelOption<'a>) -> Result<(), GeneveErr> {
+        if self.hdr.options_total_len() + option.total_len() > MAX_OPTIONS_LEN {
+            return Err(GeneveErr::OptionsTooLong);
+        }
+        self.hdr.options.get_or_insert_with(Vec::new).push(option);
+        Ok(())
+    }
+    // Swaps the inner frame while keeping the header. Like `from_parts`, `payload` then holds
+    // only the new frame and `payload_offset()` is 0, the original buffer is no longer referenced
+    pub fn replace_payload(&mut self, new_payload: &'a [u8]) {
+        self.payload = new_payload;
+        self.offset = 0;
+    }
+    // Encapsulated frame following the Geneve header and options
+    pub fn inner_payload(&self) -> &'a [u8] {
+        &self.payload[self.offset..]
+    }
+    // Encoded header and options as received, everything before `payload_offset()`. Empty for
+    // packets built with `from_parts`, and includes the outer headers for `unmarshal_at`
+    pub fn header_bytes(&self) -> &'a [u8] {
+        &self.payload[..self.offset]
+    }
+    // Received bytes after the header and options, same as `inner_payload()`
+    pub fn payload_bytes(&self) -> &'a [u8] {
+        &self.payload[self.offset..]
+    }
+    // Inner protocol and frame, ready to hand to an Ethernet or IP parser
+    pub fn decapsulate(&self) -> (ProtocolType, &'a [u8]) {
+        (self.hdr.protocol_type(), self.inner_payload())
+    }
+    // IP version nibble of the inner packet when the protocol is IPv4 or IPv6, which carry no
+    // Ethernet header. None for other protocols, an empty payload or a nibble other than 4 or 6
+    pub fn inner_ip_version(&self) -> Option<u8> {
+        if !self.hdr.protocol_is_ip() {
+            return None;
+        }
+        match self.inner_payload().first()? >> 4 {
+            i @ (4 | 6) => Some(i),
+            _ => None,
+        }
+    }
+    // Encoded size of the header, options and inner payload
+    pub fn total_len(&self) -> usize {
+        self.hdr.header_len() + self.inner_payload().len()
+    }
+    pub fn marshal(&self, buffer: &mut Vec<u8>) -> Result<(), GeneveErr> {
+        buffer.reserve(self.total_len());
+        self.hdr.marshal(buffer)?;
+        buffer.extend_from_slice(self.inner_payload());
+        Ok(())
+    }
+    pub fn marshal_to_slice(&self, buffer: &mut [u8]) -> Result<usize, GeneveErr> {
+        let payload = self.inner_payload();
+        if buffer.len() < self.total_len() {
+            return Err(GeneveErr::OutputTooSmall {
+                needed: self.total_len(),
+                got: buffer.len(),
+            });
+        }
+        let pos = self.hdr.marshal_to_slice(buffer)?;
+        buffer[pos..pos + payload.len()].copy_from_slice(payload);
+        Ok(pos + payload.len())
+    }
+    // Payload offset from the fixed header alone, options are not parsed
+    pub fn peek(buffer: &[u8]) -> Result<usize, GeneveErr> {
+        FixedHeader::peek(buffer)
+    }
+    // Same deep copy as `to_buf`, named for handing a packet to a `'static` task
+    pub fn to_static(&self) -> GenevePacketBuf {
+        self.to_buf()
+    }
+    // Copies header, options and inner payload into an owned packet
+    pub fn to_buf(&self) -> GenevePacketBuf {
+        GenevePacketBuf {
+            hdr: self.hdr.to_buf(),
+            payload: self.inner_payload().to_vec(),
+        }
+    }
+    // Writes the same bytes as `marshal`, returns the number of bytes written
+    #[cfg(feature = "std")]
+    pub fn write_to<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<usize> {
+        let len = self.hdr.write_to(w)?;
+        w.write_all(self.inner_payload())?;
+        Ok(len + self.inner_payload().len())
+    }
+    // Starts assembling a packet from a header and an inner payload
+    pub fn builder(hdr: Header<'a>) -> GenevePacketBuilder<'a> {
+        GenevePacketBuilder::new(hdr)
+    }
+    // Bounds the inner payload by an outer length, dropping trailing padding of the datagram
+    pub fn with_payload_len(buffer: &'a [u8], payload_len: usize) -> Result<Self, GeneveErr> {
+        let mut pckt = GenevePacket::unmarshal(buffer)?;
+        let end = match pckt.offset.checked_add(payload_len) {
+            Some(i) if i <= buffer.len() => i,
+            // A length past usize::MAX cannot fit any buffer either
+            i => {
+                return Err(GeneveErr::BufferTooShort {
+                    needed: i.unwrap_or(usize::MAX),
+                    got: buffer.len(),
+                })
+            }
+        };
+        pckt.payload = &buffer[..end];
+        Ok(pckt)
+    }
+    // Every problem found in `buffer` rather than only the first, for diagnostic tooling.
+    // Empty when the packet passes `Header::unmarshal_strict`
+    pub fn diagnose(buffer: &[u8]) -> Vec<GeneveErr> {
+        let mut issues = Vec::new();
+        if !is_minimum_length(buffer.len()) {
+            issues.push(GeneveErr::BufferTooShort {
+                needed: MIN_GENEVE_HDR,
+                got: buffer.len(),
+            });
+            return issues;
+        }
+        if let Err(e) = GeneveVersion::try_from(buffer[0] >> 6) {
+            issues.push(e);
+        }
+        if buffer[1] & 0x3f != 0 || buffer[7] != 0 {
+            issues.push(GeneveErr::ReservedBitsSet);
+        }
+        if buffer[2..4] == [0x00, 0x00] {
+            issues.push(GeneveErr::InvalidProtocol);
+        }
+        let opts_end = MIN_GENEVE_HDR + (buffer[0] & 0x3f) as usize * 4;
+        if opts_end > buffer.len() {
+            issues.push(GeneveErr::BufferTooShort {
+                needed: opts_end,
+                got: buffer.len(),
+            });
+        }
+        // Checks whatever part of the options region is present
+        let region = &buffer[MIN_GENEVE_HDR..opts_end.min(buffer.len())];
+        if let Err(e) = parse_options(region, |_| Ok(())) {
+            issues.push(e);
+        }
+        issues
+    }
+    // Packet plus the number of header and option bytes consumed, the inner frame starts there
+    pub fn parse(buffer: &'a [u8]) -> Result<(Self, usize), GeneveErr> {
+        let pckt = GenevePacket::unmarshal(buffer)?;
+        let consumed = pckt.offset;
+        Ok((pckt, consumed))
+    }
+    pub fn unmarshal(buffer: &'a [u8]) -> Result<Self, GeneveErr> {
+        GenevePacket::unmarshal_at(buffer, 0)
+    }
+    // Parses the Geneve header starting at `offset` of a whole frame, e.g. the UDP payload
+    // offset, keeping `payload` and `payload_offset()` relative to the start of the frame
+    pub fn unmarshal_at(buffer: &'a [u8], offset: usize) -> Result<Self, GeneveErr> {
+        let geneve = buffer
+            .get(offset..)
+            .ok_or_else(|| GeneveErr::BufferTooShort {
+                needed: offset.saturating_add(MIN_GENEVE_HDR),
+                got: buffer.len(),
+            })?;
+        // Sizes are reported against the whole frame
+        let (i, cur) = Header::unmarshal(geneve).map_err(|e| match e {
+            GeneveErr::BufferTooShort { needed, got } => GeneveErr::BufferTooShort {
+                needed: offset.saturating_add(needed),
+                got: offset.saturating_add(got),
+            },
+            e => e,
+        })?;
+        let pckt = GenevePacket {
+            hdr: i,
+            offset: offset + cur,
+            payload: buffer,
+        };
+        Ok(pckt)
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<'a> TryFrom<&'a [u8]> for GenevePacket<'a> {
     type Error = GeneveErr;
     fn try_from(packet: &'a [u8]) -> Result<Self, Self::Error> {
-        if let Some((i, k)) = Header::unmarshal(packet) {
-            Ok(GenevePacket {
-                hdr: i,
-                offset: k,
-                payload: packet,
-            })
-        } else {
-            Err(Self::Error::NotGeneve)
+        GenevePacket::unmarshal(packet)
+    }
+}
+// Appends each packet in turn and returns the encoded length of each, for scatter-gather
+// or segmentation offload descriptors. `out` is left unchanged on error
+#[cfg(feature = "alloc")]
+pub fn marshal_batch(packets: &[GenevePacket], out: &mut Vec<u8>) -> Result<Vec<usize>, GeneveErr> {
+    let start = out.len();
+    let mut lens = Vec::with_capacity(packets.len());
+    for i in packets {
+        let pos = out.len();
+        if let Err(e) = i.marshal(out) {
+            out.truncate(start);
+            return Err(e);
+        }
+        lens.push(out.len() - pos);
+    }
+    Ok(lens)
+}
+
+// Option (class, type) pairs an endpoint understands, registered once and reused
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OptionRegistry {
+    known: Vec<(u16, u8)>,
+}
+
+#[cfg(feature = "alloc")]
+impl OptionRegistry {
+    pub fn new() -> Self {
+        OptionRegistry::default()
+    }
+    pub fn register(&mut self, option_class: u16, option_type: u8) -> &mut Self {
+        if !self.contains(option_class, option_type) {
+            self.known.push((option_class, option_type));
+        }
+        self
+    }
+    pub fn contains(&self, option_class: u16, option_type: u8) -> bool {
+        self.known.contains(&(option_class, option_type))
+    }
+}
+
+// Builder for GenevePacket, encodes the header followed by the inner payload
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct GenevePacketBuilder<'a> {
+    hdr: Header<'a>,
+    payload: &'a [u8],
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> GenevePacketBuilder<'a> {
+    pub fn new(hdr: Header<'a>) -> Self {
+        GenevePacketBuilder { hdr, payload: &[] }
+    }
+    pub fn payload(mut self, payload: &'a [u8]) -> Self {
+        self.payload = payload;
+        self
+    }
+    // Packet view holding only the inner payload, so `payload_offset()` is 0
+    pub fn build(self) -> GenevePacket<'a> {
+        GenevePacket::from_parts(self.hdr, self.payload)
+    }
+    // Appends the encoded packet, `buffer` is left unchanged on error
+    pub fn build_into(&self, buffer: &mut Vec<u8>) -> Result<(), GeneveErr> {
+        self.hdr.marshal(buffer)?;
+        buffer.extend_from_slice(self.payload);
+        Ok(())
+    }
+    pub fn build_to_slice(&self, buffer: &mut [u8]) -> Result<usize, GeneveErr> {
+        let len = self.hdr.total_len(self.payload.len());
+        if buffer.len() < len {
+            return Err(GeneveErr::OutputTooSmall {
+                needed: len,
+                got: buffer.len(),
+            });
         }
+        let pos = self.hdr.marshal_to_slice(buffer)?;
+        buffer[pos..len].copy_from_slice(self.payload);
+        Ok(len)
     }
 }
+
 //   Geneve Header:
 //      +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 //      |Ver|  Opt Len  |O|C|    Rsvd.  |          Protocol Type        |
@@ -82,235 +436,2768 @@ impl<'a> TryFrom<&'a [u8]> for GenevePacket<'a> {
 //      |                    Variable Length Options                    |
 //      +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 
-#[derive(Debug, PartialEq)]
-pub struct Header {
+// Fixed 8-byte part of the header, parsed and encoded without allocating. Options are
+// walked with `OptionsIter` or parsed into a caller's slice with `unmarshal_into`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FixedHeader {
     pub version: u8,
     pub control_flag: bool,
     pub critical_flag: bool,
+    pub reserved1: u8,
     pub protocol: u16,
     pub vni: u32,
-    pub options: Option<Vec<TunnelOption>>,
-    pub options_len: u8,
+    pub reserved2: u8,
 }
 
-impl Header {
-    pub fn marshal(&self, buffer: &mut Vec<u8>) {
-        let mut opt_buffer = vec![];
-        if let Some(i) = &self.options {
-            for i in i.iter() {
-                i.marshal(&mut opt_buffer);
+impl FixedHeader {
+    // Fixed header plus the end of the declared options region, which must be in `buffer`
+    pub fn unmarshal(buffer: &[u8]) -> Result<(Self, usize), GeneveErr> {
+        if !is_minimum_length(buffer.len()) {
+            return Err(GeneveErr::BufferTooShort {
+                needed: MIN_GENEVE_HDR,
+                got: buffer.len(),
+            });
+        }
+        let data = FixedHeader {
+            version: GeneveVersion::try_from(buffer[0] >> 6)?.into(),
+            control_flag: matches!(buffer[1] >> 7, 1),
+            critical_flag: matches!((buffer[1] & 0x40) >> 6, 1),
+            reserved1: buffer[1] & 0x3f,
+            protocol: read_u16_be(buffer, 2),
+            vni: read_u24_be(buffer, 4),
+            reserved2: buffer[7],
+        };
+        let opts_end = MIN_GENEVE_HDR + (buffer[0] & 0x3f) as usize * 4;
+        if opts_end > buffer.len() {
+            // Declared options run past the end of the buffer
+            return Err(GeneveErr::BufferTooShort {
+                needed: opts_end,
+                got: buffer.len(),
+            });
+        }
+        Ok((data, opts_end))
+    }
+    // Parses options into `opts` without allocating. Returns the fixed header, the bytes
+    // consumed and the number of options written to `opts`
+    pub fn unmarshal_into<'a>(
+        buffer: &'a [u8],
+        opts: &mut [TunnelOption<'a>],
+    ) -> Result<(Self, usize, usize), GeneveErr> {
+        let (data, cursor) = FixedHeader::unmarshal(buffer)?;
+        let mut count = 0;
+        parse_options(&buffer[MIN_GENEVE_HDR..cursor], |k| {
+            // Options past the last slot are still counted to report how many are needed
+            if let Some(slot) = opts.get_mut(count) {
+                *slot = k;
             }
+            count += 1;
+            Ok(())
+        })?;
+        if count > opts.len() {
+            return Err(GeneveErr::OptionSlotsTooFew {
+                needed: count,
+                got: opts.len(),
+            });
         }
-        buffer.push((&self.version << 6) | (((opt_buffer.len() / 4) as u8) & 0x3f));
-        match (&self.control_flag, &self.critical_flag) {
-            (false, false) => buffer.push(0x00),
-            (true, false) => buffer.push(0x80),
-            (false, true) => buffer.push(0x40),
-            (true, true) => buffer.push(0xc0),
-        }
-        buffer.extend_from_slice(&self.protocol.to_be_bytes());
-        buffer.extend_from_slice(&self.vni.to_be_bytes()[1..]);
-        buffer.push(0x00);
-        buffer.extend_from_slice(&opt_buffer[..]);
-    }
-    pub fn unmarshal(buffer: &[u8]) -> Option<(Self, usize)> {
-        if buffer.len() >= MIN_GENEVE_HDR {
-            let mut cursor: usize = MIN_GENEVE_HDR;
-            let data = Header {
-                version: match buffer[0] >> 6 {
-                    0 => 0,
-                    _ => return None,
-                },
-                control_flag: matches!(buffer[1] >> 7, 1),
-                critical_flag: matches!((buffer[1] & 0x40) >> 6, 1),
-                protocol: u16::from_be_bytes([buffer[2], buffer[3]]),
-                vni: u32::from_be_bytes([0x00, buffer[4], buffer[5], buffer[6]]),
-                options: match ((buffer[0] & 0x3f) * 4) as usize {
-                    0 => None,
-                    i => {
-                        if i <= (buffer.len() - MIN_GENEVE_HDR) {
-                            let mut vector: Vec<TunnelOption> = vec![];
-                            while let Some(k) = TunnelOption::unmarshal(
-                                &buffer[cursor..(((buffer[0] & 0x3f) * 4) + 8).into()],
-                            ) {
-                                cursor += k.advance();
-                                vector.push(k);
-                            }
-                            Some(vector)
-                        } else {
-                            None
-                        }
-                    }
-                },
-                options_len: (buffer[0] & 0x3f) * 4,
-            };
-            Some((data, cursor))
-        } else {
-            None
+        Ok((data, cursor, count))
+    }
+    // Payload offset from the fixed header alone, options are not parsed
+    pub fn peek(buffer: &[u8]) -> Result<usize, GeneveErr> {
+        if !is_minimum_length(buffer.len()) {
+            return Err(GeneveErr::BufferTooShort {
+                needed: MIN_GENEVE_HDR,
+                got: buffer.len(),
+            });
+        }
+        GeneveVersion::try_from(buffer[0] >> 6)?;
+        let offset = MIN_GENEVE_HDR + (buffer[0] & 0x3f) as usize * 4;
+        if offset > buffer.len() {
+            return Err(GeneveErr::BufferTooShort {
+                needed: offset,
+                got: buffer.len(),
+            });
+        }
+        Ok(offset)
+    }
+    // Version bits of the first byte whether or not the version is supported, so tooling can
+    // log what it saw before dropping. Needs only one byte, unlike `version_of`
+    pub fn raw_version(buffer: &[u8]) -> Option<u8> {
+        buffer.first().map(|i| i >> 6)
+    }
+    // Version bits of an encoded header without parsing the rest, for quick filtering
+    pub fn version_of(buffer: &[u8]) -> Option<u8> {
+        match is_minimum_length(buffer.len()) {
+            true => Some(buffer[0] >> 6),
+            false => None,
+        }
+    }
+    // Options region of an encoded header, for forwarding options verbatim
+    pub fn raw_options(buffer: &[u8]) -> Option<&[u8]> {
+        options_region(buffer)
+    }
+    // Number of options in an encoded header, reading only each option's length field.
+    // None unless the options exactly fill the declared options region
+    pub fn count_options(buffer: &[u8]) -> Option<usize> {
+        let region = options_region(buffer)?;
+        let (mut cursor, mut count) = (0, 0);
+        while cursor < region.len() {
+            let len_field = *region.get(cursor + 3)?;
+            cursor += MIN_OPT_SIZE + (len_field & 0x1f) as usize * 4;
+            count += 1;
+        }
+        match cursor == region.len() {
+            true => Some(count),
+            false => None,
         }
     }
+    // Overwrites the VNI of an encoded header, leaving flags, options and payload untouched
+    pub fn rewrite_vni_in_place(buffer: &mut [u8], vni: u32) -> Result<(), GeneveErr> {
+        let vni = Vni::new(vni)?;
+        if !is_minimum_length(buffer.len()) {
+            return Err(GeneveErr::BufferTooShort {
+                needed: MIN_GENEVE_HDR,
+                got: buffer.len(),
+            });
+        }
+        write_u24_be(buffer, 4, vni.u32());
+        Ok(())
+    }
+    // Fixed header with the options length field set for `options_len` bytes of options
+    pub fn encode(&self, options_len: usize) -> [u8; MIN_GENEVE_HDR] {
+        let first = (((self.version & 0x03) as u32) << 30)
+            | ((((options_len / 4) as u32) & 0x3f) << 24)
+            | ((self.control_flag as u32) << 23)
+            | ((self.critical_flag as u32) << 22)
+            | (((self.reserved1 & 0x3f) as u32) << 16)
+            | self.protocol as u32;
+        // Bits of the VNI above 24 are shifted out, as with the 3-byte field on the wire
+        let second = (self.vni << 8) | self.reserved2 as u32;
+        (((first as u64) << 32) | second as u64).to_be_bytes()
+    }
+    // Fixed header followed by `options` in order, returns the number of bytes written
+    pub fn marshal_to_slice(
+        &self,
+        options: &[TunnelOption],
+        buffer: &mut [u8],
+    ) -> Result<usize, GeneveErr> {
+        let options_len = options.iter().map(|i| i.total_len()).sum::<usize>();
+        if options_len > MAX_OPTIONS_LEN {
+            return Err(GeneveErr::OptionsTooLong);
+        }
+        if buffer.len() < MIN_GENEVE_HDR + options_len {
+            return Err(GeneveErr::OutputTooSmall {
+                needed: MIN_GENEVE_HDR + options_len,
+                got: buffer.len(),
+            });
+        }
+        buffer[..MIN_GENEVE_HDR].copy_from_slice(&self.encode(options_len));
+        let mut pos = MIN_GENEVE_HDR;
+        for i in options {
+            pos += i.marshal_to_slice(&mut buffer[pos..])?;
+        }
+        Ok(pos)
+    }
 }
 
-//  Geneve Option:
-//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
-//   |          Option Class         |      Type     |R|R|R| Length  |
-//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
-//   |                      Variable Option Data                     |
-//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
-
-#[derive(Debug, PartialEq)]
-pub struct TunnelOption {
-    pub option_class: u16,
-    pub option_type: u8,
-    pub c_flag: bool,
-    pub data: Option<Vec<u8>>,
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Header<'a> {
+    pub version: u8,
+    // O bit, set on OAM (control) packets
+    pub control_flag: bool,
+    // C bit, set when any option carries its own critical bit
+    pub critical_flag: bool,
+    pub reserved1: u8,
+    pub protocol: u16,
+    pub vni: u32,
+    pub reserved2: u8,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub options: Option<Vec<TunnelOption<'a>>>,
 }
 
-impl TunnelOption {
-    pub fn marshal(&self, buffer: &mut Vec<u8>) {
-        buffer.extend_from_slice(&self.option_class.to_be_bytes());
-        match self.c_flag {
-            true => buffer.push(0x80 | self.option_type),
-            false => buffer.push(0x7f & self.option_type),
+#[cfg(feature = "alloc")]
+impl<'a> Header<'a> {
+    pub const MAX_OPTIONS_LEN: usize = MAX_OPTIONS_LEN;
+    pub const MAX_SIZE: usize = MAX_HEADER_LEN;
+    // Length of all options as encoded on the wire, including padding
+    pub fn options_total_len(&self) -> usize {
+        self.options().iter().map(|i| i.total_len()).sum()
+    }
+    #[deprecated(note = "use `options_total_len`")]
+    pub fn opt_len(&self) -> usize {
+        self.options_total_len()
+    }
+    // Replaces the former `options_len` field, always derived from `options`. A hand-built
+    // header can exceed 255 bytes of options, so this is not narrowed to the field's old `u8`
+    pub fn options_len(&self) -> usize {
+        self.options_total_len()
+    }
+    // Options as a slice, empty when the header carries none
+    pub fn options(&self) -> &[TunnelOption<'a>] {
+        self.options.as_deref().unwrap_or_default()
+    }
+    // Length of the fixed header plus options
+    pub fn header_len(&self) -> usize {
+        MIN_GENEVE_HDR + self.options_total_len()
+    }
+    // Bytes `marshal` appends, reserved up front so large option sets do not regrow the Vec
+    pub fn estimate_marshal_len(&self) -> usize {
+        self.header_len()
+    }
+    // Size of the header followed by a payload of `payload_len` bytes
+    pub fn total_len(&self, payload_len: usize) -> usize {
+        self.header_len() + payload_len
+    }
+    pub fn to_buf(&self) -> HeaderBuf {
+        HeaderBuf {
+            version: self.version,
+            control_flag: self.control_flag,
+            critical_flag: self.critical_flag,
+            reserved1: self.reserved1,
+            protocol: self.protocol,
+            vni: self.vni,
+            reserved2: self.reserved2,
+            options: self
+                .options
+                .as_ref()
+                .map(|i| i.iter().map(|k| k.to_buf()).collect()),
         }
-        if let Some(i) = &self.data {
-            match &i.len() % 4 {
-                0 => {
-                    buffer.push((i.len() / 4) as u8);
-                    buffer.extend_from_slice(&i[..]);
-                }
-                _ => {
-                    buffer.push(((i.len() + (4 - (i.len() % 4))) / 4) as u8);
-                    buffer.extend_from_slice(&i[..]);
-                    buffer.extend_from_slice(&vec![0; 4 - (i.len() % 4)]);
+    }
+    // Encoded fixed header, for hashing or diffing without a full marshal
+    pub fn fixed_header_bytes(&self) -> [u8; MIN_GENEVE_HDR] {
+        self.encode_header()
+    }
+    // Fixed 8-byte part of the header, assembled as a single u64 store
+    fn encode_header(&self) -> [u8; MIN_GENEVE_HDR] {
+        self.fixed().encode(self.options_total_len())
+    }
+    // Header fields without the options
+    pub fn fixed(&self) -> FixedHeader {
+        FixedHeader {
+            version: self.version,
+            control_flag: self.control_flag,
+            critical_flag: self.critical_flag,
+            reserved1: self.reserved1,
+            protocol: self.protocol,
+            vni: self.vni,
+            reserved2: self.reserved2,
+        }
+    }
+    // Header from its fixed fields and options, an empty Vec is stored as `None`
+    pub fn from_fixed(fixed: FixedHeader, options: Vec<TunnelOption<'a>>) -> Self {
+        Header {
+            version: fixed.version,
+            control_flag: fixed.control_flag,
+            critical_flag: fixed.critical_flag,
+            reserved1: fixed.reserved1,
+            protocol: fixed.protocol,
+            vni: fixed.vni,
+            reserved2: fixed.reserved2,
+            options: match options.is_empty() {
+                true => None,
+                false => Some(options),
+            },
+        }
+    }
+    // Options are written in `options` order, repeated class/type pairs included
+    pub fn marshal(&self, buffer: &mut Vec<u8>) -> Result<(), GeneveErr> {
+        if self.options_total_len() > MAX_OPTIONS_LEN {
+            return Err(GeneveErr::OptionsTooLong);
+        }
+        buffer.reserve(self.estimate_marshal_len());
+        let start = buffer.len();
+        buffer.extend_from_slice(&self.encode_header());
+        for i in self.options.iter().flatten() {
+            if let Err(e) = i.marshal(buffer) {
+                buffer.truncate(start);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+    pub fn marshal_to_slice(&self, buffer: &mut [u8]) -> Result<usize, GeneveErr> {
+        self.fixed().marshal_to_slice(self.options(), buffer)
+    }
+    // Like `marshal_to_slice`, returning the unwritten tail of `buffer` for chained writes
+    pub fn marshal_into<'b>(&self, buffer: &'b mut [u8]) -> Result<&'b mut [u8], GeneveErr> {
+        let pos = self.marshal_to_slice(buffer)?;
+        Ok(&mut buffer[pos..])
+    }
+    pub fn protocol_type(&self) -> ProtocolType {
+        ProtocolType::from(self.protocol)
+    }
+    pub fn protocol_is_ethernet(&self) -> bool {
+        self.protocol_type() == ProtocolType::Ethernet
+    }
+    // IPv4 or IPv6
+    pub fn protocol_is_ip(&self) -> bool {
+        matches!(
+            self.protocol_type(),
+            ProtocolType::Ipv4 | ProtocolType::Ipv6
+        )
+    }
+    pub fn set_protocol_type(&mut self, protocol: ProtocolType) {
+        self.protocol = protocol.into();
+    }
+    // Opt-in RFC 8926 conformance check, marshal itself accepts any reserved or protocol value
+    pub fn validate(&self) -> Result<(), GeneveErr> {
+        self.geneve_version()?;
+        if self.reserved1 != 0 || self.reserved2 != 0 {
+            return Err(GeneveErr::ReservedBitsSet);
+        }
+        if self.protocol == 0 {
+            return Err(GeneveErr::InvalidProtocol);
+        }
+        Ok(())
+    }
+    // Opt-in fix-up before marshal: RFC 8926 requires the C bit whenever an option is
+    // critical. A C bit that is already set is left alone
+    pub fn normalize(&mut self) {
+        if self.options.iter().flatten().any(|i| i.c_flag) {
+            self.critical_flag = true;
+        }
+    }
+    // Opt-in stable sort of options by (class, type) for caching or comparison. Wire order
+    // is otherwise preserved, so this changes the encoded bytes whenever options move
+    pub fn canonicalize(&mut self) {
+        if let Some(options) = self.options.as_mut() {
+            options.sort_by_key(|i| (i.option_class, i.option_type));
+        }
+    }
+    // RFC 8926 name of the O bit, same as `control_flag`
+    pub fn oam(&self) -> bool {
+        self.control_flag
+    }
+    // RFC 8926 name of the C bit, same as `critical_flag`
+    pub fn has_critical_options(&self) -> bool {
+        self.critical_flag
+    }
+    // Sets a VNI already checked to fit the 24-bit field
+    pub fn set_vni(&mut self, vni: Vni) {
+        self.vni = vni.u32();
+    }
+    // Writes the same bytes as `marshal`, returns the number of bytes written
+    #[cfg(feature = "std")]
+    pub fn write_to<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<usize> {
+        let mut buffer = [0; MAX_HEADER_LEN];
+        let len = self.marshal_to_slice(&mut buffer)?;
+        w.write_all(&buffer[..len])?;
+        Ok(len)
+    }
+    // First option matching the given class and type
+    pub fn find_option(&self, option_class: u16, option_type: u8) -> Option<&TunnelOption<'a>> {
+        self.find_options(option_class, option_type).next()
+    }
+    // First option matching the given class and type, for in-place rewrites. Replacement
+    // data must outlive the header ('a), `options_len()` follows any change in length
+    pub fn option_mut(
+        &mut self,
+        option_class: u16,
+        option_type: u8,
+    ) -> Option<&mut TunnelOption<'a>> {
+        self.options
+            .iter_mut()
+            .flatten()
+            .find(|i| i.option_class == option_class && i.option_type == option_type)
+    }
+    pub fn clear_options(&mut self) {
+        self.options = None;
+    }
+    // Removes the first option matching the given class and type, `options` becomes
+    // None once the last one is gone
+    pub fn remove_option(&mut self, option_class: u16, option_type: u8) -> bool {
+        let Some(options) = self.options.as_mut() else {
+            return false;
+        };
+        match options
+            .iter()
+            .position(|i| i.option_class == option_class && i.option_type == option_type)
+        {
+            Some(i) => {
+                options.remove(i);
+                if options.is_empty() {
+                    self.options = None;
                 }
-            };
-        } else {
-            buffer.push(0x00);
+                true
+            }
+            None => false,
+        }
+    }
+    // All options matching the given class and type, in wire order
+    pub fn find_options(
+        &self,
+        option_class: u16,
+        option_type: u8,
+    ) -> impl Iterator<Item = &TunnelOption<'a>> {
+        self.options
+            .iter()
+            .flatten()
+            .filter(move |i| i.option_class == option_class && i.option_type == option_type)
+    }
+    // All options of the given class regardless of type, in wire order
+    pub fn options_by_class(&self, option_class: u16) -> impl Iterator<Item = &TunnelOption<'a>> {
+        self.options
+            .iter()
+            .flatten()
+            .filter(move |i| i.option_class == option_class)
+    }
+    // Options region of an encoded header, for forwarding options verbatim
+    pub fn raw_options(buffer: &[u8]) -> Option<&[u8]> {
+        FixedHeader::raw_options(buffer)
+    }
+    // Iterates over the options of an encoded header without allocating
+    pub fn options_iter(buffer: &'a [u8]) -> Option<OptionsIter<'a>> {
+        OptionsIter::new(buffer)
+    }
+    // Number of options in an encoded header, reading only each option's length field.
+    // None unless the options exactly fill the declared options region
+    pub fn count_options(buffer: &[u8]) -> Option<usize> {
+        FixedHeader::count_options(buffer)
+    }
+    // Overwrites the VNI of an encoded header, leaving flags, options and payload untouched
+    pub fn rewrite_vni_in_place(buffer: &mut [u8], vni: u32) -> Result<(), GeneveErr> {
+        FixedHeader::rewrite_vni_in_place(buffer, vni)
+    }
+    // `version` as a GeneveVersion, parsed and built headers are always version 0 but the
+    // field is public and may have been set by hand
+    pub fn geneve_version(&self) -> Result<GeneveVersion, GeneveErr> {
+        GeneveVersion::try_from(self.version)
+    }
+    // Version bits of the first byte whether or not the version is supported, so tooling can
+    // log what it saw before dropping. Needs only one byte, unlike `version_of`
+    pub fn raw_version(buffer: &[u8]) -> Option<u8> {
+        FixedHeader::raw_version(buffer)
+    }
+    // Version bits of an encoded header without parsing the rest, for quick filtering
+    pub fn version_of(buffer: &[u8]) -> Option<u8> {
+        FixedHeader::version_of(buffer)
+    }
+    // Like `unmarshal`, but also rejects set reserved bits and the reserved protocol 0x0000
+    pub fn unmarshal_strict(buffer: &'a [u8]) -> Result<(Self, usize), GeneveErr> {
+        let (hdr, cursor) = Header::unmarshal(buffer)?;
+        hdr.validate()?;
+        Ok((hdr, cursor))
+    }
+    pub fn unmarshal(buffer: &'a [u8]) -> Result<(Self, usize), GeneveErr> {
+        let (mut data, cursor) = Header::unmarshal_fixed(buffer)?;
+        if cursor > MIN_GENEVE_HDR {
+            // Upper bound on the option count, avoids regrowing while pushing
+            let mut vector: Vec<TunnelOption<'a>> =
+                Vec::with_capacity((cursor - MIN_GENEVE_HDR) / MIN_OPT_SIZE);
+            parse_options(&buffer[MIN_GENEVE_HDR..cursor], |k| {
+                vector.push(k);
+                Ok(())
+            })?;
+            data.options = Some(vector);
         }
+        Ok((data, cursor))
+    }
+    // Parses options into `opts` instead of a Vec, the returned header has `options: None`.
+    // Returns the header, the bytes consumed and the number of options written to `opts`
+    pub fn unmarshal_into(
+        buffer: &'a [u8],
+        opts: &mut [TunnelOption<'a>],
+    ) -> Result<(Self, usize, usize), GeneveErr> {
+        let (fixed, cursor, count) = FixedHeader::unmarshal_into(buffer, opts)?;
+        Ok((Header::from_fixed(fixed, Vec::new()), cursor, count))
+    }
+    // Fixed header with `options: None`, plus the end of the declared options region
+    fn unmarshal_fixed(buffer: &'a [u8]) -> Result<(Self, usize), GeneveErr> {
+        let (fixed, opts_end) = FixedHeader::unmarshal(buffer)?;
+        Ok((Header::from_fixed(fixed, Vec::new()), opts_end))
     }
+}
 
-    pub fn unmarshal(buffer: &[u8]) -> Option<Self> {
-        if buffer.len() >= 4 {
-            let data = TunnelOption {
-                option_class: u16::from_be_bytes([buffer[0], buffer[1]]),
-                option_type: 0x7f & buffer[2],
-                c_flag: matches!(buffer[2] >> 7, 1),
-                data: match ((buffer[3] & 0x1f) * 4) as usize {
-                    0 => None,
-                    i if i <= (buffer.len() - 4) => Some(buffer[4..4 + i].to_vec()),
-                    _ => return None,
-                },
-            };
-            Some(data)
-        } else {
-            None
+// Walks an options region, which must be exactly filled by well-formed options
+fn parse_options<'a>(
+    buffer: &'a [u8],
+    mut f: impl FnMut(TunnelOption<'a>) -> Result<(), GeneveErr>,
+) -> Result<(), GeneveErr> {
+    let mut cursor = 0;
+    while let Some(k) = TunnelOption::unmarshal(&buffer[cursor..]) {
+        // `unmarshal` only returns options that fit the rest of the region, at least 4 bytes
+        cursor += k.advance();
+        f(k)?;
+    }
+    // Parsing stopped before the end of the region, so the last option did not fit
+    if cursor != buffer.len() {
+        return Err(GeneveErr::TruncatedOption);
+    }
+    Ok(())
+}
+
+// Virtual Network Identifier, guaranteed to fit the 24-bit wire field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Vni(u32);
+
+impl Vni {
+    pub fn new(vni: u32) -> Result<Self, GeneveErr> {
+        if vni > MAX_VNI {
+            return Err(GeneveErr::InvalidVni);
         }
+        Ok(Vni(vni))
     }
+    pub fn u32(&self) -> u32 {
+        self.0
+    }
+}
 
-    pub fn advance(&self) -> usize {
-        match &self.data {
-            Some(i) => match &i.len() % 4 {
-                0 => i.len() + 4,
-                _ => (i.len() + (4 - (i.len() % 4))) / 4,
-            },
-            None => 4,
+impl From<Vni> for u32 {
+    fn from(vni: Vni) -> Self {
+        vni.0
+    }
+}
+
+// Blank header carrying Ethernet (Transparent Ethernet Bridging), the most common payload
+#[cfg(feature = "alloc")]
+impl Default for Header<'_> {
+    fn default() -> Self {
+        Header {
+            version: 0,
+            control_flag: false,
+            critical_flag: false,
+            reserved1: 0,
+            protocol: ProtocolType::Ethernet.into(),
+            vni: 0,
+            reserved2: 0,
+            options: None,
         }
     }
 }
 
-#[test]
-fn tunnel_options_marshal() {
-    let decoded = TunnelOption {
-        option_class: 0xffff,
-        option_type: 0x0a,
-        c_flag: false,
-        data: Some(vec![0x00, 0x01]),
-    };
-    let encoded: [u8; 8] = [0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00, 0x00];
-    let mut buffer: Vec<u8> = vec![];
-    decoded.marshal(&mut buffer);
-    assert_eq!(buffer, encoded);
+// Discards the cursor, use `Header::unmarshal` to also get the payload offset
+#[cfg(feature = "alloc")]
+impl<'a> TryFrom<&'a [u8]> for Header<'a> {
+    type Error = GeneveErr;
+    fn try_from(buffer: &'a [u8]) -> Result<Self, Self::Error> {
+        Header::unmarshal(buffer).map(|(i, _)| i)
+    }
 }
 
-#[test]
-fn tunnel_options_unmarshal() {
-    let encoded: [u8; 8] = [0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00, 0x00];
-    let decoded = TunnelOption {
-        option_class: 0xffff,
-        option_type: 0x0a,
-        c_flag: false,
-        data: Some(vec![0x00, 0x01, 0x00, 0x00]),
-    };
-    if let Some(i) = TunnelOption::unmarshal(&encoded) {
-        assert_eq!(i, decoded);
+// `for option in &hdr`, yields nothing when the header carries no options
+#[cfg(feature = "alloc")]
+impl<'a, 'b> IntoIterator for &'b Header<'a> {
+    type Item = &'b TunnelOption<'a>;
+    type IntoIter = core::slice::Iter<'b, TunnelOption<'a>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.options().iter()
     }
 }
 
-#[test]
-fn geneve_header_marshal() {
-    let decoded = Header {
-        version: 0,
-        control_flag: false,
-        critical_flag: false,
-        protocol: 0x86dd,
-        vni: 0x00aaaaee,
-        options: Some(vec![
-            TunnelOption {
-                option_class: 0xffff,
-                option_type: 0x0a,
-                c_flag: false,
-                data: Some(vec![0x00, 0x01, 0x00, 0x00]),
-            },
-            TunnelOption {
-                option_class: 0xffff,
-                option_type: 0x0b,
-                c_flag: false,
-                data: Some(vec![0x00, 0x02, 0x00, 0x00]),
-            },
-        ]),
-        options_len: 0,
-    };
-    let encoded: [u8; 24] = [
-        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
-        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
-    ];
-    let mut buffer: Vec<u8> = vec![];
-    decoded.marshal(&mut buffer);
-    assert_eq!(buffer, encoded);
+// Multi-line summary for debugging, option data is printed as hex
+#[cfg(feature = "alloc")]
+impl fmt::Display for Header<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Geneve v{} O={} C={} len={}",
+            self.version,
+            self.control_flag as u8,
+            self.critical_flag as u8,
+            self.header_len()
+        )?;
+        writeln!(
+            f,
+            "  protocol: 0x{:04x} ({:?})",
+            self.protocol,
+            self.protocol_type()
+        )?;
+        write!(f, "  vni: 0x{:06x}", self.vni)?;
+        for i in self.options.iter().flatten() {
+            write!(
+                f,
+                "\n  option: class=0x{:04x} type=0x{:02x} c={} len={} data=",
+                i.option_class,
+                i.option_type,
+                i.c_flag as u8,
+                i.data_len()
+            )?;
+            for k in i.data.unwrap_or_default() {
+                write!(f, "{:02x}", k)?;
+            }
+        }
+        Ok(())
+    }
 }
 
-#[test]
-fn geneve_header_unmarshal() {
-    let decoded = Header {
-        version: 0,
-        control_flag: false,
+// Marshalled packet as one continuous lowercase hex string. The header goes through a stack
+// buffer of MAX_HEADER_LEN bytes, the inner payload is written straight from the packet
+#[cfg(feature = "alloc")]
+impl fmt::LowerHex for GenevePacket<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buffer = [0; MAX_HEADER_LEN];
+        let len = self
+            .hdr
+            .marshal_to_slice(&mut buffer)
+            .map_err(|_| fmt::Error)?;
+        for i in buffer[..len].iter().chain(self.inner_payload()) {
+            write!(f, "{:02x}", i)?;
+        }
+        Ok(())
+    }
+}
+
+// Protocol Type of the encapsulated payload, expressed as an EtherType
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProtocolType {
+    Ethernet,
+    Ipv4,
+    Arp,
+    Ipv6,
+    Mpls,
+    Nsh,
+    Unknown(u16),
+}
+
+impl From<u16> for ProtocolType {
+    fn from(protocol: u16) -> Self {
+        match protocol {
+            0x6558 => ProtocolType::Ethernet,
+            0x0800 => ProtocolType::Ipv4,
+            0x0806 => ProtocolType::Arp,
+            0x86dd => ProtocolType::Ipv6,
+            0x8847 => ProtocolType::Mpls,
+            0x894f => ProtocolType::Nsh,
+            i => ProtocolType::Unknown(i),
+        }
+    }
+}
+
+impl From<ProtocolType> for u16 {
+    fn from(protocol: ProtocolType) -> Self {
+        match protocol {
+            ProtocolType::Ethernet => 0x6558,
+            ProtocolType::Ipv4 => 0x0800,
+            ProtocolType::Arp => 0x0806,
+            ProtocolType::Ipv6 => 0x86dd,
+            ProtocolType::Mpls => 0x8847,
+            ProtocolType::Nsh => 0x894f,
+            ProtocolType::Unknown(i) => i,
+        }
+    }
+}
+
+// Geneve version, only version 0 is defined by RFC 8926
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum GeneveVersion {
+    V0,
+}
+
+// The single place deciding which versions are supported
+impl TryFrom<u8> for GeneveVersion {
+    type Error = GeneveErr;
+    fn try_from(version: u8) -> Result<Self, Self::Error> {
+        match version {
+            0 => Ok(GeneveVersion::V0),
+            i => Err(GeneveErr::UnsupportedVersion(i)),
+        }
+    }
+}
+
+impl From<GeneveVersion> for u8 {
+    fn from(version: GeneveVersion) -> Self {
+        match version {
+            GeneveVersion::V0 => 0,
+        }
+    }
+}
+
+// Option Class as registered with IANA. Organizations holding several classes and
+// experimental classes keep their raw value, so the conversion back to u16 is lossless.
+// New registrations may be added without a breaking release
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum OptionClass {
+    Linux,
+    OpenVSwitch,
+    Ovn,
+    Int,
+    Vmware,
+    // 0x0105 and 0x0108 to 0x0110, 0x0108 is used by AWS Gateway Load Balancer
+    Amazon(u16),
+    // 0x0106
+    Cisco(u16),
+    Oracle,
+    // 0x0111 to 0x0118
+    Ibm(u16),
+    // 0x0119 to 0x0128
+    Ericsson(u16),
+    Oxide,
+    // 0xff00 to 0xffff, reserved for experimental use by RFC 8926 section 7.2
+    Experimental(u16),
+    Unknown(u16),
+}
+
+impl From<u16> for OptionClass {
+    fn from(class: u16) -> Self {
+        // IANA "Geneve Option Class" registry, created by RFC 8926 section 7.2: 0x0000 to
+        // 0x00ff by IETF Review, 0x0100 to 0xfeff First Come First Served, 0xff00 to 0xffff
+        // Experimental Use
+        match class {
+            0x0100 => OptionClass::Linux,
+            0x0101 => OptionClass::OpenVSwitch,
+            0x0102 => OptionClass::Ovn,
+            0x0103 => OptionClass::Int,
+            0x0104 => OptionClass::Vmware,
+            i @ (0x0105 | 0x0108..=0x0110) => OptionClass::Amazon(i),
+            i @ 0x0106 => OptionClass::Cisco(i),
+            0x0107 => OptionClass::Oracle,
+            i @ 0x0111..=0x0118 => OptionClass::Ibm(i),
+            i @ 0x0119..=0x0128 => OptionClass::Ericsson(i),
+            0x0129 => OptionClass::Oxide,
+            i @ 0xff00..=0xffff => OptionClass::Experimental(i),
+            i => OptionClass::Unknown(i),
+        }
+    }
+}
+
+impl From<OptionClass> for u16 {
+    fn from(class: OptionClass) -> Self {
+        match class {
+            OptionClass::Linux => 0x0100,
+            OptionClass::OpenVSwitch => 0x0101,
+            OptionClass::Ovn => 0x0102,
+            OptionClass::Int => 0x0103,
+            OptionClass::Vmware => 0x0104,
+            OptionClass::Oracle => 0x0107,
+            OptionClass::Oxide => 0x0129,
+            OptionClass::Amazon(i)
+            | OptionClass::Cisco(i)
+            | OptionClass::Ibm(i)
+            | OptionClass::Ericsson(i)
+            | OptionClass::Experimental(i)
+            | OptionClass::Unknown(i) => i,
+        }
+    }
+}
+
+// Builder for Header
+#[cfg(feature = "alloc")]
+#[derive(Debug, Default)]
+pub struct HeaderBuilder<'a> {
+    version: u8,
+    control_flag: bool,
+    critical_flag: bool,
+    protocol: u16,
+    vni: u32,
+    options: Vec<TunnelOption<'a>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> HeaderBuilder<'a> {
+    pub fn new() -> Self {
+        HeaderBuilder::default()
+    }
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+    pub fn control_flag(mut self, flag: bool) -> Self {
+        self.control_flag = flag;
+        self
+    }
+    pub fn critical_flag(mut self, flag: bool) -> Self {
+        self.critical_flag = flag;
+        self
+    }
+    pub fn protocol(mut self, protocol: u16) -> Self {
+        self.protocol = protocol;
+        self
+    }
+    pub fn vni(mut self, vni: u32) -> Self {
+        self.vni = vni;
+        self
+    }
+    pub fn add_option(mut self, option: TunnelOption<'a>) -> Self {
+        self.options.push(option);
+        self
+    }
+    pub fn build(self) -> Result<Header<'a>, GeneveErr> {
+        // Only version 0 is defined, other values would be masked to 2 bits when encoding
+        GeneveVersion::try_from(self.version)?;
+        let vni = Vni::new(self.vni)?;
+        if self.options.iter().map(|i| i.total_len()).sum::<usize>() > MAX_OPTIONS_LEN {
+            return Err(GeneveErr::OptionsTooLong);
+        }
+        Ok(Header {
+            version: self.version,
+            control_flag: self.control_flag,
+            critical_flag: self.critical_flag,
+            reserved1: 0,
+            protocol: self.protocol,
+            vni: vni.u32(),
+            reserved2: 0,
+            options: match self.options.is_empty() {
+                true => None,
+                false => Some(self.options),
+            },
+        })
+    }
+}
+
+//  Geneve Option:
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |          Option Class         |      Type     |R|R|R| Length  |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |                      Variable Option Data                     |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TunnelOption<'a> {
+    pub option_class: u16,
+    pub option_type: u8,
+    pub c_flag: bool,
+    pub data: Option<&'a [u8]>,
+}
+
+impl<'a> TunnelOption<'a> {
+    // Preferred constructor, validates the data length up front instead of at marshal time
+    pub fn new(
+        option_class: u16,
+        option_type: u8,
+        c_flag: bool,
+        data: Option<&'a [u8]>,
+    ) -> Result<Self, GeneveErr> {
+        if option_type > MAX_OPTION_TYPE {
+            return Err(GeneveErr::InvalidOptionType);
+        }
+        if let Some(i) = data {
+            if i.len() > MAX_DATA_SIZE {
+                return Err(GeneveErr::OptionTooLong);
+            }
+        }
+        Ok(TunnelOption {
+            option_class,
+            option_type,
+            c_flag,
+            data,
+        })
+    }
+    pub fn class(&self) -> OptionClass {
+        OptionClass::from(self.option_class)
+    }
+    pub fn data_len(&self) -> usize {
+        self.data.map_or(0, |i| i.len())
+    }
+    // Equality as seen on the wire. Unlike the derived `PartialEq`, data is compared after
+    // padding both sides to 4 bytes, so a constructed option matches its parsed form
+    pub fn eq_semantic(&self, other: &TunnelOption) -> bool {
+        self.option_class == other.option_class
+            && self.option_type == other.option_type
+            && self.c_flag == other.c_flag
+            && self.data_words() == other.data_words()
+            && self.padded_data().eq(other.padded_data())
+    }
+    // Data bytes followed by the zero padding written on the wire
+    fn padded_data(&self) -> impl Iterator<Item = u8> + '_ {
+        self.data
+            .unwrap_or_default()
+            .iter()
+            .copied()
+            .chain(core::iter::repeat_n(0, self.padding_len()))
+    }
+    // Data with all trailing zero bytes dropped, a best-effort guess at the data before
+    // padding. Zeros that belong to the data itself are dropped as well
+    pub fn data_without_trailing_zeros(&self) -> Option<&'a [u8]> {
+        let data = self.data?;
+        let len = data.iter().rposition(|i| *i != 0).map_or(0, |i| i + 1);
+        Some(&data[..len])
+    }
+    // Big-endian views of the data, None unless its length matches exactly
+    pub fn data_as_u32(&self) -> Option<u32> {
+        self.data?.try_into().ok().map(u32::from_be_bytes)
+    }
+    pub fn data_as_u64(&self) -> Option<u64> {
+        self.data?.try_into().ok().map(u64::from_be_bytes)
+    }
+    pub fn data_as_ipv4(&self) -> Option<core::net::Ipv4Addr> {
+        self.data_as_u32().map(core::net::Ipv4Addr::from)
+    }
+    pub fn data_as_ipv6(&self) -> Option<core::net::Ipv6Addr> {
+        let octets: [u8; 16] = self.data?.try_into().ok()?;
+        Some(core::net::Ipv6Addr::from(octets))
+    }
+    // Zero bytes appended after the data to reach 4-byte alignment
+    pub fn padding_len(&self) -> usize {
+        (4 - self.data_len() % 4) % 4
+    }
+    // Length field written on the wire: padded data in 4-byte words. Only meaningful
+    // while the data fits MAX_DATA_SIZE, marshal rejects anything larger
+    pub fn data_words(&self) -> u8 {
+        ((self.data_len() + self.padding_len()) / 4) as u8
+    }
+    // Encoded length of the option: header, data and padding
+    pub fn total_len(&self) -> usize {
+        MIN_OPT_SIZE + self.data_len() + self.padding_len()
+    }
+    #[deprecated(note = "use `total_len`, this is not the option length field")]
+    pub fn opt_len(&self) -> usize {
+        self.total_len()
+    }
+    // Option header, the length field counts the padded data in 4-byte words
+    fn encode_opt(&self) -> Result<[u8; MIN_OPT_SIZE], GeneveErr> {
+        if self.data_len() > MAX_DATA_SIZE {
+            return Err(GeneveErr::OptionTooLong);
+        }
+        if self.option_type > MAX_OPTION_TYPE {
+            return Err(GeneveErr::InvalidOptionType);
+        }
+        let mut opt = [0; MIN_OPT_SIZE];
+        write_u16_be(&mut opt, 0, self.option_class);
+        opt[2] = match self.c_flag {
+            true => 0x80 | self.option_type,
+            false => self.option_type,
+        };
+        opt[3] = self.data_words();
+        Ok(opt)
+    }
+    pub fn marshal_to_slice(&self, buffer: &mut [u8]) -> Result<usize, GeneveErr> {
+        let opt = self.encode_opt()?;
+        if buffer.len() < self.total_len() {
+            return Err(GeneveErr::OutputTooSmall {
+                needed: self.total_len(),
+                got: buffer.len(),
+            });
+        }
+        buffer[..MIN_OPT_SIZE].copy_from_slice(&opt);
+        let pos = MIN_OPT_SIZE + self.data_len();
+        if let Some(i) = self.data {
+            buffer[MIN_OPT_SIZE..pos].copy_from_slice(i);
+        }
+        buffer[pos..self.total_len()].fill(0);
+        Ok(self.total_len())
+    }
+    // Complete encoded option including padding, written to the front of `buffer`
+    pub fn as_bytes<'b>(&self, buffer: &'b mut [u8]) -> Result<&'b [u8], GeneveErr> {
+        let len = self.marshal_to_slice(buffer)?;
+        Ok(&buffer[..len])
+    }
+    // Same bytes as `marshal_to_slice` for data already a multiple of 4 bytes, no padding is written
+    pub fn marshal_prealigned(&self, buffer: &mut [u8]) -> Result<usize, GeneveErr> {
+        let data = self.data.unwrap_or_default();
+        if self.padding_len() != 0 {
+            return Err(GeneveErr::UnalignedData);
+        }
+        let opt = self.encode_opt()?;
+        let len = MIN_OPT_SIZE + data.len();
+        if buffer.len() < len {
+            return Err(GeneveErr::OutputTooSmall {
+                needed: len,
+                got: buffer.len(),
+            });
+        }
+        buffer[..MIN_OPT_SIZE].copy_from_slice(&opt);
+        buffer[MIN_OPT_SIZE..len].copy_from_slice(data);
+        Ok(len)
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn marshal(&self, buffer: &mut Vec<u8>) -> Result<(), GeneveErr> {
+        buffer.extend_from_slice(&self.encode_opt()?);
+        if let Some(i) = self.data {
+            buffer.extend_from_slice(i);
+            buffer.extend_from_slice(&[0; 4][..self.padding_len()]);
+        }
+        Ok(())
+    }
+
+    // `data` covers the whole padded length: the wire format does not record the length
+    // before padding, so trailing zeros cannot be told apart from padding
+    pub fn unmarshal(buffer: &'a [u8]) -> Option<Self> {
+        if buffer.len() >= MIN_OPT_SIZE {
+            let data = TunnelOption {
+                option_class: read_u16_be(buffer, 0),
+                option_type: 0x7f & buffer[2],
+                c_flag: matches!(buffer[2] >> 7, 1),
+                data: match (buffer[3] & 0x1f) as usize * 4 {
+                    0 => None,
+                    i if i <= MAX_DATA_SIZE && i <= (buffer.len() - MIN_OPT_SIZE) => {
+                        Some(&buffer[MIN_OPT_SIZE..MIN_OPT_SIZE + i])
+                    }
+                    _ => return None,
+                },
+            };
+            Some(data)
+        } else {
+            None
+        }
+    }
+
+    // Bytes to skip past this option when walking the options region
+    pub fn advance(&self) -> usize {
+        self.total_len()
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn to_buf(&self) -> TunnelOptionBuf {
+        TunnelOptionBuf {
+            option_class: self.option_class,
+            option_type: self.option_type,
+            c_flag: self.c_flag,
+            data: self.data.map(|i| i.to_vec()),
+        }
+    }
+
+    // Lazily parses options from raw option bytes without allocating
+    pub fn iter(buffer: &'a [u8]) -> OptionsIter<'a> {
+        OptionsIter { buffer, cursor: 0 }
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for TunnelOption<'a> {
+    type Error = GeneveErr;
+    fn try_from(buffer: &'a [u8]) -> Result<Self, Self::Error> {
+        // Fails only when the option header or its declared data is truncated
+        TunnelOption::unmarshal(buffer).ok_or(GeneveErr::InvalidLength)
+    }
+}
+
+// (option_class, option_type, c_flag, data), no validation is done in either direction
+impl<'a> From<TunnelOption<'a>> for (u16, u8, bool, Option<&'a [u8]>) {
+    fn from(option: TunnelOption<'a>) -> Self {
+        (
+            option.option_class,
+            option.option_type,
+            option.c_flag,
+            option.data,
+        )
+    }
+}
+
+impl<'a> From<(u16, u8, bool, Option<&'a [u8]>)> for TunnelOption<'a> {
+    fn from((option_class, option_type, c_flag, data): (u16, u8, bool, Option<&'a [u8]>)) -> Self {
+        TunnelOption {
+            option_class,
+            option_type,
+            c_flag,
+            data,
+        }
+    }
+}
+
+// Iterator over the options region of a Geneve header
+#[derive(Debug)]
+pub struct OptionsIter<'a> {
+    buffer: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> OptionsIter<'a> {
+    // Iterates over the options declared by an encoded header, usable without an allocator
+    pub fn new(buffer: &'a [u8]) -> Option<Self> {
+        options_region(buffer).map(TunnelOption::iter)
+    }
+}
+
+// Options bytes declared by the length field of an encoded header
+fn options_region(buffer: &[u8]) -> Option<&[u8]> {
+    if is_minimum_length(buffer.len()) {
+        let opts_end = MIN_GENEVE_HDR + (buffer[0] & 0x3f) as usize * 4;
+        if opts_end <= buffer.len() {
+            return Some(&buffer[MIN_GENEVE_HDR..opts_end]);
+        }
+    }
+    None
+}
+
+impl<'a> Iterator for OptionsIter<'a> {
+    type Item = TunnelOption<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cursor >= self.buffer.len() {
+            return None;
+        }
+        match TunnelOption::unmarshal(&self.buffer[self.cursor..]) {
+            Some(i) => {
+                self.cursor += i.advance();
+                Some(i)
+            }
+            None => {
+                self.cursor = self.buffer.len();
+                None
+            }
+        }
+    }
+}
+
+// Owned counterparts of the borrowed types, for packets outliving their source buffer
+// and as a serde deserialization target
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeaderBuf {
+    pub version: u8,
+    pub control_flag: bool,
+    pub critical_flag: bool,
+    pub reserved1: u8,
+    pub protocol: u16,
+    pub vni: u32,
+    pub reserved2: u8,
+    pub options: Option<Vec<TunnelOptionBuf>>,
+}
+
+#[cfg(feature = "alloc")]
+impl HeaderBuf {
+    pub fn as_header(&self) -> Header<'_> {
+        Header {
+            version: self.version,
+            control_flag: self.control_flag,
+            critical_flag: self.critical_flag,
+            reserved1: self.reserved1,
+            protocol: self.protocol,
+            vni: self.vni,
+            reserved2: self.reserved2,
+            options: self
+                .options
+                .as_ref()
+                .map(|i| i.iter().map(|k| k.as_option()).collect()),
+        }
+    }
+}
+
+// Owned counterpart of GenevePacket, `payload` holds only the inner frame
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GenevePacketBuf {
+    pub hdr: HeaderBuf,
+    pub payload: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl GenevePacketBuf {
+    // Borrowed view, its `payload` holds only the inner frame so `payload_offset()` is 0
+    pub fn as_packet(&self) -> GenevePacket<'_> {
+        GenevePacket::from_parts(self.hdr.as_header(), &self.payload)
+    }
+    pub fn marshal(&self, buffer: &mut Vec<u8>) -> Result<(), GeneveErr> {
+        self.hdr.as_header().marshal(buffer)?;
+        buffer.extend_from_slice(&self.payload);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TunnelOptionBuf {
+    pub option_class: u16,
+    pub option_type: u8,
+    pub c_flag: bool,
+    pub data: Option<Vec<u8>>,
+}
+
+#[cfg(feature = "alloc")]
+impl TunnelOptionBuf {
+    pub fn as_option(&self) -> TunnelOption<'_> {
+        TunnelOption {
+            option_class: self.option_class,
+            option_type: self.option_type,
+            c_flag: self.c_flag,
+            data: self.data.as_deref(),
+        }
+    }
+}
+
+#[test]
+fn tunnel_options_marshal() {
+    let decoded = TunnelOption {
+        option_class: 0xffff,
+        option_type: 0x0a,
+        c_flag: false,
+        data: Some(&[0x00, 0x01]),
+    };
+    let encoded: [u8; 8] = [0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00, 0x00];
+    let mut buffer: Vec<u8> = vec![];
+    decoded.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded);
+}
+
+#[test]
+fn tunnel_options_unmarshal_max_length_field() {
+    // Length field 0x1f with the reserved bits above it set: 124 data bytes, no wraparound
+    let mut encoded = vec![0x01, 0x08, 0x01, 0xff];
+    encoded.extend_from_slice(&[0xaa; MAX_DATA_SIZE]);
+    let option = TunnelOption::unmarshal(&encoded).unwrap();
+    assert_eq!(option.data_len(), MAX_DATA_SIZE);
+    assert_eq!(option.advance(), MIN_OPT_SIZE + MAX_DATA_SIZE);
+    assert_eq!(option.data_words(), 0x1f);
+    assert!(TunnelOption::unmarshal(&encoded[..MIN_OPT_SIZE + MAX_DATA_SIZE - 1]).is_none());
+}
+
+#[test]
+fn tunnel_options_as_bytes() {
+    let decoded = TunnelOption {
+        option_class: 0xffff,
+        option_type: 0x0a,
+        c_flag: false,
+        data: Some(&[0x00, 0x01]),
+    };
+    let encoded: [u8; 8] = [0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00, 0x00];
+    let mut buffer = [0xee; 16];
+    assert_eq!(decoded.as_bytes(&mut buffer).unwrap(), encoded);
+    assert!(matches!(
+        decoded.as_bytes(&mut [0; 7]),
+        Err(GeneveErr::OutputTooSmall { needed: 8, got: 7 })
+    ));
+}
+
+#[test]
+fn tunnel_options_unmarshal() {
+    let encoded: [u8; 8] = [0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00, 0x00];
+    let decoded = TunnelOption {
+        option_class: 0xffff,
+        option_type: 0x0a,
+        c_flag: false,
+        data: Some(&[0x00, 0x01, 0x00, 0x00]),
+    };
+    if let Some(i) = TunnelOption::unmarshal(&encoded) {
+        assert_eq!(i, decoded);
+    }
+}
+
+#[test]
+fn tunnel_options_try_from() {
+    let encoded: [u8; 8] = [0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00, 0x00];
+    let option = TunnelOption::try_from(&encoded[..]).unwrap();
+    assert_eq!(option.option_type, 0x0a);
+    assert_eq!(option.data, Some(&[0x00, 0x01, 0x00, 0x00][..]));
+    assert!(matches!(
+        TunnelOption::try_from(&encoded[..6]),
+        Err(GeneveErr::InvalidLength)
+    ));
+    assert!(matches!(
+        TunnelOption::try_from(&encoded[..2]),
+        Err(GeneveErr::InvalidLength)
+    ));
+}
+
+#[test]
+fn tunnel_options_invalid_type() {
+    assert!(matches!(
+        TunnelOption::new(0x0108, 0x80, false, None),
+        Err(GeneveErr::InvalidOptionType)
+    ));
+    let option = TunnelOption::new(0x0108, 0x7f, true, None).unwrap();
+    let mut buffer: Vec<u8> = vec![];
+    option.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, [0x01, 0x08, 0xff, 0x00]);
+    let overflowed = TunnelOption {
+        option_class: 0x0108,
+        option_type: 0x80,
+        c_flag: false,
+        data: None,
+    };
+    buffer.clear();
+    assert!(matches!(
+        overflowed.marshal(&mut buffer),
+        Err(GeneveErr::InvalidOptionType)
+    ));
+    assert!(buffer.is_empty());
+    let mut slice = [0; 4];
+    assert!(matches!(
+        overflowed.marshal_to_slice(&mut slice),
+        Err(GeneveErr::InvalidOptionType)
+    ));
+}
+
+#[test]
+fn tunnel_options_tuple() {
+    let option = TunnelOption::new(0x0108, 0x01, true, Some(&[0x00, 0x01])).unwrap();
+    let tuple: (u16, u8, bool, Option<&[u8]>) = option.clone().into();
+    assert_eq!(tuple, (0x0108, 0x01, true, Some(&[0x00, 0x01][..])));
+    assert_eq!(TunnelOption::from(tuple), option);
+    let (option_class, _, _, data) = tuple;
+    assert_eq!(option_class, 0x0108);
+    assert_eq!(data.map(|i| i.len()), Some(2));
+}
+
+#[test]
+fn tunnel_options_padding_len() {
+    let data = [0xab; 8];
+    for (len, padding) in [(0, 0), (1, 3), (3, 1), (4, 0), (5, 3), (8, 0)] {
+        let option = TunnelOption::new(0xffff, 0x0a, false, Some(&data[..len])).unwrap();
+        assert_eq!(option.padding_len(), padding);
+        assert_eq!(option.total_len(), MIN_OPT_SIZE + len + padding);
+        let mut buffer: Vec<u8> = vec![];
+        option.marshal(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), option.total_len());
+        let mut slice = [0xee; 16];
+        assert_eq!(
+            option.marshal_to_slice(&mut slice).unwrap(),
+            option.total_len()
+        );
+        assert_eq!(slice[..option.total_len()], buffer[..]);
+    }
+    let empty = TunnelOption::new(0xffff, 0x0a, false, None).unwrap();
+    assert_eq!(empty.padding_len(), 0);
+}
+
+#[test]
+#[allow(deprecated)]
+fn tunnel_options_total_len() {
+    let data = [0xab; 8];
+    for len in [0, 1, 4, 5, 8] {
+        let option = TunnelOption::new(0xffff, 0x0a, false, Some(&data[..len])).unwrap();
+        assert_eq!(option.total_len(), option.opt_len());
+    }
+    let hdr = HeaderBuilder::new()
+        .add_option(TunnelOption::new(0xffff, 0x0a, false, Some(&data[..5])).unwrap())
+        .add_option(TunnelOption::new(0xffff, 0x0b, false, None).unwrap())
+        .build()
+        .unwrap();
+    assert_eq!(hdr.options_total_len(), 16);
+    assert_eq!(hdr.options_total_len(), hdr.opt_len());
+    assert_eq!(Header::default().options_total_len(), 0);
+}
+
+#[test]
+fn tunnel_options_marshal_to_slice_padding() {
+    let cases: [(&[u8], [u8; 8]); 3] = [
+        (&[0x01], [0xff, 0xff, 0x0a, 0x01, 0x01, 0x00, 0x00, 0x00]),
+        (
+            &[0x01, 0x02],
+            [0xff, 0xff, 0x0a, 0x01, 0x01, 0x02, 0x00, 0x00],
+        ),
+        (
+            &[0x01, 0x02, 0x03],
+            [0xff, 0xff, 0x0a, 0x01, 0x01, 0x02, 0x03, 0x00],
+        ),
+    ];
+    for (data, encoded) in cases {
+        let option = TunnelOption::new(0xffff, 0x0a, false, Some(data)).unwrap();
+        let mut slice = [0xee; 10];
+        assert_eq!(option.marshal_to_slice(&mut slice).unwrap(), 8);
+        assert_eq!(option.total_len(), 8);
+        assert_eq!(slice[..8], encoded);
+        // Bytes past the option are left untouched
+        assert_eq!(slice[8..], [0xee, 0xee]);
+    }
+}
+
+#[test]
+fn tunnel_options_marshal_unaligned() {
+    let option =
+        TunnelOption::new(0xffff, 0x0a, false, Some(&[0x01, 0x02, 0x03, 0x04, 0x05])).unwrap();
+    let encoded: [u8; 12] = [
+        0xff, 0xff, 0x0a, 0x02, 0x01, 0x02, 0x03, 0x04, 0x05, 0x00, 0x00, 0x00,
+    ];
+    let mut buffer: Vec<u8> = vec![];
+    option.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded);
+    assert_eq!(buffer.len(), option.total_len());
+    let mut slice = [0xee; 12];
+    assert_eq!(option.marshal_to_slice(&mut slice).unwrap(), 12);
+    assert_eq!(slice, encoded);
+}
+
+#[test]
+fn tunnel_options_marshal_prealigned() {
+    let mut buffer = [0xee; 12];
+    let unaligned =
+        TunnelOption::new(0xffff, 0x0a, false, Some(&[0x01, 0x02, 0x03, 0x04, 0x05])).unwrap();
+    assert!(matches!(
+        unaligned.marshal_prealigned(&mut buffer),
+        Err(GeneveErr::UnalignedData)
+    ));
+    assert_eq!(buffer, [0xee; 12]);
+    let aligned = TunnelOption::new(0xffff, 0x0a, false, Some(&[0x01, 0x02, 0x03, 0x04])).unwrap();
+    assert_eq!(aligned.marshal_prealigned(&mut buffer).unwrap(), 8);
+    let mut expected = [0; 8];
+    aligned.marshal_to_slice(&mut expected).unwrap();
+    assert_eq!(buffer[..8], expected);
+    assert!(matches!(
+        aligned.marshal_prealigned(&mut buffer[..7]),
+        Err(GeneveErr::OutputTooSmall { needed: 8, got: 7 })
+    ));
+    let empty = TunnelOption::new(0xffff, 0x0a, false, None).unwrap();
+    assert_eq!(empty.marshal_prealigned(&mut buffer).unwrap(), 4);
+}
+
+#[test]
+fn tunnel_options_advance() {
+    let aligned = TunnelOption {
+        option_class: 0xffff,
+        option_type: 0x0a,
+        c_flag: false,
+        data: Some(&[0x00, 0x01, 0x00, 0x00]),
+    };
+    let unaligned = TunnelOption {
+        option_class: 0xffff,
+        option_type: 0x0a,
+        c_flag: false,
+        data: Some(&[0x00, 0x01, 0x02, 0x03, 0x04, 0x05]),
+    };
+    let empty = TunnelOption {
+        option_class: 0xffff,
+        option_type: 0x0a,
+        c_flag: false,
+        data: None,
+    };
+    assert_eq!(aligned.advance(), 8);
+    assert_eq!(unaligned.advance(), 12);
+    assert_eq!(empty.advance(), 4);
+}
+
+#[test]
+fn tunnel_options_max_data_size() {
+    let data = [0xab; 128];
+    for len in [120, 124] {
+        let decoded = TunnelOption {
+            option_class: 0x0108,
+            option_type: 0x01,
+            c_flag: false,
+            data: Some(&data[..len]),
+        };
+        let mut buffer: Vec<u8> = vec![];
+        decoded.marshal(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), MIN_OPT_SIZE + len);
+        assert_eq!(buffer[3] as usize, len / 4);
+        assert_eq!(TunnelOption::unmarshal(&buffer), Some(decoded));
+    }
+    let oversized = TunnelOption {
+        option_class: 0x0108,
+        option_type: 0x01,
+        c_flag: false,
+        data: Some(&data[..]),
+    };
+    let mut buffer: Vec<u8> = vec![];
+    assert!(matches!(
+        oversized.marshal(&mut buffer),
+        Err(GeneveErr::OptionTooLong)
+    ));
+    assert!(buffer.is_empty());
+    // 32 words does not fit the 5-bit length field: 0x20 is a reserved bit over a length of 0,
+    // so this decodes as an option without data and never as 128 bytes
+    let mut encoded = vec![0x01, 0x08, 0x01, 0x20];
+    encoded.extend_from_slice(&[0xab; 128]);
+    let decoded = TunnelOption::unmarshal(&encoded).unwrap();
+    assert_eq!(
+        decoded,
+        TunnelOption {
+            option_class: 0x0108,
+            option_type: 0x01,
+            c_flag: false,
+            data: None,
+        }
+    );
+    assert_eq!(decoded.advance(), MIN_OPT_SIZE);
+}
+
+#[test]
+fn tunnel_options_length_field() {
+    let data = [0xab; 256];
+    for len in [200, 255, 256] {
+        let oversized = TunnelOption {
+            option_class: 0x0108,
+            option_type: 0x01,
+            c_flag: false,
+            data: Some(&data[..len]),
+        };
+        let mut buffer: Vec<u8> = vec![];
+        assert!(matches!(
+            oversized.marshal(&mut buffer),
+            Err(GeneveErr::OptionTooLong)
+        ));
+        assert!(buffer.is_empty());
+        let mut slice = [0; 512];
+        assert!(matches!(
+            oversized.marshal_to_slice(&mut slice),
+            Err(GeneveErr::OptionTooLong)
+        ));
+        assert_eq!(slice[3], 0);
+    }
+    for (len, words) in [(0, 0), (1, 1), (4, 1), (5, 2), (124, 31)] {
+        let option = TunnelOption {
+            option_class: 0x0108,
+            option_type: 0x01,
+            c_flag: false,
+            data: Some(&data[..len]),
+        };
+        let mut buffer: Vec<u8> = vec![];
+        option.marshal(&mut buffer).unwrap();
+        assert_eq!(buffer[3], words);
+    }
+}
+
+#[test]
+fn tunnel_options_data_words() {
+    let data = [0xab; MAX_DATA_SIZE];
+    for (len, words) in [(0, 0), (4, 1), (5, 2), (124, 31)] {
+        let option = TunnelOption::new(0x0108, 0x01, false, Some(&data[..len])).unwrap();
+        assert_eq!(option.data_words(), words);
+        let mut buffer: Vec<u8> = vec![];
+        option.marshal(&mut buffer).unwrap();
+        assert_eq!(buffer[3], option.data_words());
+    }
+    let empty = TunnelOption::new(0x0108, 0x01, false, None).unwrap();
+    assert_eq!(empty.data_words(), 0);
+}
+
+#[test]
+fn tunnel_options_data_views() {
+    let encoded: [u8; 8] = [0x01, 0x08, 0x01, 0x01, 0xc0, 0xa8, 0x00, 0x01];
+    let option = TunnelOption::unmarshal(&encoded).unwrap();
+    assert_eq!(
+        option.data_as_ipv4(),
+        Some(core::net::Ipv4Addr::new(192, 168, 0, 1))
+    );
+    assert_eq!(option.data_as_u32(), Some(0xc0a80001));
+    assert_eq!(option.data_as_u64(), None);
+    assert_eq!(option.data_as_ipv6(), None);
+    let data = [
+        0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01,
+    ];
+    let option = TunnelOption::new(0x0108, 0x01, false, Some(&data)).unwrap();
+    assert_eq!(
+        option.data_as_ipv6(),
+        Some(core::net::Ipv6Addr::new(0x2001, 0x0db8, 0, 0, 0, 0, 0, 1))
+    );
+    assert_eq!(option.data_as_ipv4(), None);
+    let option = TunnelOption::new(0x0108, 0x01, false, Some(&data[..8])).unwrap();
+    assert_eq!(option.data_as_u64(), Some(0x20010db800000000));
+    let empty = TunnelOption::new(0x0108, 0x01, false, None).unwrap();
+    assert_eq!(empty.data_as_u32(), None);
+}
+
+#[test]
+fn tunnel_options_eq_semantic() {
+    let constructed = TunnelOption::new(0xffff, 0x0a, false, Some(&[0x00, 0x01])).unwrap();
+    let encoded: [u8; 8] = [0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00, 0x00];
+    let parsed = TunnelOption::unmarshal(&encoded).unwrap();
+    assert_ne!(constructed, parsed);
+    assert!(constructed.eq_semantic(&parsed));
+    assert!(parsed.eq_semantic(&constructed));
+    let different = TunnelOption::new(0xffff, 0x0a, false, Some(&[0x00, 0x01, 0x01])).unwrap();
+    assert!(!different.eq_semantic(&parsed));
+    let longer = TunnelOption::new(0xffff, 0x0a, false, Some(&[0x00, 0x01, 0, 0, 0])).unwrap();
+    assert!(!longer.eq_semantic(&parsed));
+    let critical = TunnelOption::new(0xffff, 0x0a, true, Some(&[0x00, 0x01])).unwrap();
+    assert!(!critical.eq_semantic(&parsed));
+    let empty = TunnelOption::new(0xffff, 0x0a, false, None).unwrap();
+    let empty_slice = TunnelOption::new(0xffff, 0x0a, false, Some(&[])).unwrap();
+    assert!(empty.eq_semantic(&empty_slice));
+}
+
+#[test]
+fn tunnel_options_data_without_trailing_zeros() {
+    let option = TunnelOption::new(0xffff, 0x0a, false, Some(&[0x01, 0x02])).unwrap();
+    let mut buffer: Vec<u8> = vec![];
+    option.marshal(&mut buffer).unwrap();
+    // Parsed data carries the two padding bytes, the original length is not on the wire
+    let parsed = TunnelOption::unmarshal(&buffer).unwrap();
+    assert_eq!(parsed.data, Some(&[0x01, 0x02, 0x00, 0x00][..]));
+    assert_eq!(
+        parsed.data_without_trailing_zeros(),
+        Some(&[0x01, 0x02][..])
+    );
+    // Data ending in zeros cannot be recovered exactly
+    let encoded: [u8; 8] = [0xff, 0xff, 0x0a, 0x01, 0x01, 0x00, 0x00, 0x00];
+    let parsed = TunnelOption::unmarshal(&encoded).unwrap();
+    assert_eq!(parsed.data_without_trailing_zeros(), Some(&[0x01][..]));
+    let zeros = TunnelOption::new(0xffff, 0x0a, false, Some(&[0x00; 4])).unwrap();
+    assert_eq!(zeros.data_without_trailing_zeros(), Some(&[][..]));
+    let empty = TunnelOption::new(0xffff, 0x0a, false, None).unwrap();
+    assert_eq!(empty.data_without_trailing_zeros(), None);
+}
+
+#[test]
+fn tunnel_options_new() {
+    let data = [0xab; 125];
+    let option = TunnelOption::new(0x0108, 0x01, true, Some(&data[..124])).unwrap();
+    assert_eq!(option.data.map(|i| i.len()), Some(124));
+    assert!(option.c_flag);
+    assert!(matches!(
+        TunnelOption::new(0x0108, 0x01, false, Some(&data)),
+        Err(GeneveErr::OptionTooLong)
+    ));
+    assert!(TunnelOption::new(0x0108, 0x01, false, None).is_ok());
+}
+
+#[test]
+fn tunnel_options_iter() {
+    let encoded: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let (hdr, _) = Header::unmarshal(&encoded).unwrap();
+    let lazy: Vec<TunnelOption> = Header::options_iter(&encoded).unwrap().collect();
+    assert_eq!(Some(lazy), hdr.options);
+    // Trailing payload bytes beyond the declared options length are not parsed
+    assert_eq!(Header::options_iter(&encoded).unwrap().count(), 2);
+    // A truncated trailing option ends the iteration
+    let truncated: [u8; 10] = [0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00, 0x00, 0xff, 0xff];
+    let mut iter = TunnelOption::iter(&truncated);
+    assert_eq!(iter.next().map(|i| i.option_type), Some(0x0a));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next(), None);
+    assert!(Header::options_iter(&encoded[..20]).is_none());
+}
+
+// Core parse and encode path, available without an allocator
+#[test]
+fn tunnel_options_no_alloc() {
+    let encoded: [u8; 24] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+    ];
+    let mut buffer = [0; 16];
+    let mut pos = 0;
+    for i in OptionsIter::new(&encoded).unwrap() {
+        pos += i.marshal_to_slice(&mut buffer[pos..]).unwrap();
+    }
+    assert_eq!(pos, 16);
+    assert_eq!(buffer, encoded[MIN_GENEVE_HDR..]);
+}
+
+#[test]
+fn geneve_header_unaligned_option_roundtrip() {
+    let decoded = Header {
+        version: 0,
+        control_flag: false,
+        critical_flag: false,
+        reserved1: 0,
+        protocol: 0x86dd,
+        vni: 0x00aaaaee,
+        reserved2: 0,
+        options: Some(vec![
+            TunnelOption {
+                option_class: 0xffff,
+                option_type: 0x0a,
+                c_flag: false,
+                data: Some(&[0x00, 0x01, 0x02, 0x03, 0x04, 0x05]),
+            },
+            TunnelOption {
+                option_class: 0xffff,
+                option_type: 0x0b,
+                c_flag: false,
+                data: Some(&[0x00, 0x02, 0x00, 0x00]),
+            },
+        ]),
+    };
+    let mut buffer: Vec<u8> = vec![];
+    decoded.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer.len(), 28);
+    match Header::unmarshal(&buffer) {
+        Ok((i, cursor)) => {
+            let options = i.options.unwrap();
+            assert_eq!(options.len(), 2);
+            assert_eq!(
+                options[0].data,
+                Some(&[0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x00, 0x00][..])
+            );
+            assert_eq!(options[1].option_type, 0x0b);
+            assert_eq!(options[1].data, Some(&[0x00, 0x02, 0x00, 0x00][..]));
+            assert_eq!(cursor, 28);
+        }
+        Err(_) => panic!(),
+    }
+}
+
+#[test]
+fn geneve_header_unmarshal_adversarial_options() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for opt_len in 0..=0x3f_u8 {
+            for length_byte in 0..=0xff_u8 {
+                let mut buffer = vec![opt_len, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00];
+                for _ in 0..opt_len {
+                    buffer.extend_from_slice(&[0x00, 0x00, 0x80, length_byte]);
+                }
+                let _ = Header::unmarshal(&buffer);
+                let _ = Header::unmarshal(&buffer[..buffer.len() / 2]);
+            }
+        }
+        tx.send(()).unwrap();
+    });
+    assert!(rx.recv_timeout(std::time::Duration::from_secs(10)).is_ok());
+}
+
+#[test]
+fn geneve_header_unmarshal_truncated_option() {
+    // Final option claims 8 bytes of data but only 4 are left in the options region
+    let encoded: [u8; 24] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x02, 0x00, 0x02, 0x00, 0x00,
+    ];
+    assert!(matches!(
+        Header::unmarshal(&encoded),
+        Err(GeneveErr::TruncatedOption)
+    ));
+    assert!(matches!(
+        GenevePacket::unmarshal(&encoded),
+        Err(GeneveErr::TruncatedOption)
+    ));
+    let empty = TunnelOption::new(0, 0, false, None).unwrap();
+    let mut opts = [empty.clone(), empty.clone()];
+    assert!(matches!(
+        Header::unmarshal_into(&encoded, &mut opts),
+        Err(GeneveErr::TruncatedOption)
+    ));
+    // The lazy walk has no error channel and stops after the first option
+    assert_eq!(Header::options_iter(&encoded).unwrap().count(), 1);
+}
+
+#[test]
+fn geneve_header_unmarshal_options_mismatch() {
+    // Declares 16 bytes of options, the second option needs 12 bytes but only 8 remain
+    let under_consumed: [u8; 24] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x02, 0x00, 0x02, 0x00, 0x00,
+    ];
+    assert!(matches!(
+        Header::unmarshal(&under_consumed),
+        Err(GeneveErr::TruncatedOption)
+    ));
+    // Declares 8 bytes of options, the only option claims 8 bytes of data
+    let mid_option: [u8; 20] = [
+        0x02, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x02, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    assert!(matches!(
+        Header::unmarshal(&mid_option),
+        Err(GeneveErr::TruncatedOption)
+    ));
+    // Declares 8 bytes of options holding two data-less options
+    let exact: [u8; 16] = [
+        0x02, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x00, 0xff, 0xff, 0x0b,
+        0x00,
+    ];
+    match Header::unmarshal(&exact) {
+        Ok((i, cursor)) => {
+            assert_eq!(i.options.map(|k| k.len()), Some(2));
+            assert_eq!(cursor, 16);
+        }
+        Err(_) => panic!(),
+    }
+}
+
+#[test]
+fn geneve_header_unmarshal_max_options() {
+    // 63 data-less options fill the largest options length
+    let mut encoded = vec![0x3f, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00];
+    for i in 0..63 {
+        encoded.extend_from_slice(&[0x01, 0x08, i, 0x00]);
+    }
+    let (hdr, cursor) = Header::unmarshal(&encoded).unwrap();
+    assert_eq!(cursor, MIN_GENEVE_HDR + MAX_OPTIONS_LEN);
+    assert_eq!(MAX_HEADER_LEN, 260);
+    assert_eq!(Header::MAX_SIZE, MAX_HEADER_LEN);
+    assert!(hdr.header_len() <= MAX_HEADER_LEN);
+    let mut buffer = [0; MAX_HEADER_LEN];
+    assert_eq!(hdr.marshal_to_slice(&mut buffer).unwrap(), MAX_HEADER_LEN);
+    assert_eq!(buffer, encoded[..]);
+    let options = hdr.options();
+    assert_eq!(options.len(), 63);
+    for (i, k) in options.iter().enumerate() {
+        assert_eq!(k.option_type as usize, i);
+        assert_eq!(k.data, None);
+    }
+    // The allocation-free paths walk the same 63 options
+    assert_eq!(Header::count_options(&encoded), Some(63));
+    assert!(Header::options_iter(&encoded)
+        .unwrap()
+        .eq(options.iter().cloned()));
+    let mut opts: Vec<TunnelOption> = vec![TunnelOption::new(0, 0, false, None).unwrap(); 63];
+    let (_, cursor, count) = Header::unmarshal_into(&encoded, &mut opts).unwrap();
+    assert_eq!((cursor, count), (MAX_HEADER_LEN, 63));
+    assert_eq!(opts, options);
+}
+
+#[test]
+fn geneve_header_options_length_field_max() {
+    // Options length 0x3f with version bits clear: 8 + 252 = 260 does not wrap in u8
+    let mut encoded = vec![0x3f, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00];
+    encoded.extend_from_slice(&[0x00; MAX_OPTIONS_LEN]);
+    assert_eq!(GenevePacket::peek(&encoded).unwrap(), MAX_HEADER_LEN);
+    assert_eq!(
+        Header::raw_options(&encoded).map(|i| i.len()),
+        Some(MAX_OPTIONS_LEN)
+    );
+    assert!(matches!(
+        GenevePacket::peek(&encoded[..MAX_HEADER_LEN - 1]),
+        Err(GeneveErr::BufferTooShort {
+            needed: MAX_HEADER_LEN,
+            got: 259
+        })
+    ));
+    assert!(Header::raw_options(&encoded[..MAX_HEADER_LEN - 1]).is_none());
+}
+
+#[test]
+fn geneve_header_unmarshal_full_options_region() {
+    // Options length 0x3f filled by two large options: 4 + 124 and 4 + 120 bytes
+    let mut encoded = vec![0x3f, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00];
+    encoded.extend_from_slice(&[0x01, 0x08, 0x01, 0x1f]);
+    encoded.extend_from_slice(&[0xaa; 124]);
+    encoded.extend_from_slice(&[0x01, 0x08, 0x02, 0x1e]);
+    encoded.extend_from_slice(&[0xbb; 120]);
+    encoded.extend_from_slice(&[0xde, 0xad]);
+    let (hdr, cursor) = Header::unmarshal(&encoded).unwrap();
+    assert_eq!(cursor, MAX_HEADER_LEN);
+    let options = hdr.options();
+    assert_eq!(options.len(), 2);
+    assert_eq!(options[0].data, Some(&[0xaa; 124][..]));
+    assert_eq!(options[1].data, Some(&[0xbb; 120][..]));
+    // The lazy and counting walks end at the same bound
+    assert!(Header::options_iter(&encoded)
+        .unwrap()
+        .eq(options.iter().cloned()));
+    assert_eq!(Header::count_options(&encoded), Some(2));
+    assert_eq!(GenevePacket::peek(&encoded).unwrap(), cursor);
+    let mut buffer = [0; MAX_HEADER_LEN];
+    hdr.marshal_to_slice(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded[..cursor]);
+}
+
+#[test]
+fn geneve_header_rewrite_vni_in_place() {
+    let mut encoded: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let original = encoded;
+    Header::rewrite_vni_in_place(&mut encoded, 0x123456).unwrap();
+    assert_eq!(encoded[4..7], [0x12, 0x34, 0x56]);
+    assert_eq!(encoded[..4], original[..4]);
+    assert_eq!(encoded[7..], original[7..]);
+    let packet = GenevePacket::unmarshal(&encoded).unwrap();
+    assert_eq!(packet.hdr.vni, 0x123456);
+    assert_eq!(packet.options().len(), 2);
+    assert_eq!(packet.inner_payload(), &original[24..]);
+    assert!(matches!(
+        Header::rewrite_vni_in_place(&mut encoded, 0x01000000),
+        Err(GeneveErr::InvalidVni)
+    ));
+    assert!(matches!(
+        Header::rewrite_vni_in_place(&mut encoded[..7], 0x000001),
+        Err(GeneveErr::BufferTooShort { needed: 8, got: 7 })
+    ));
+    assert_eq!(encoded[4..7], [0x12, 0x34, 0x56]);
+}
+
+#[test]
+fn geneve_header_geneve_version() {
+    let encoded: [u8; 8] = [0x00, 0x00, 0x65, 0x58, 0x00, 0xaa, 0xaa, 0x00];
+    let (mut hdr, _) = Header::unmarshal(&encoded).unwrap();
+    assert_eq!(hdr.geneve_version().unwrap(), GeneveVersion::V0);
+    assert_eq!(u8::from(hdr.geneve_version().unwrap()), hdr.version);
+    hdr.version = 2;
+    assert!(matches!(
+        hdr.geneve_version(),
+        Err(GeneveErr::UnsupportedVersion(2))
+    ));
+    assert!(matches!(
+        GeneveVersion::try_from(1),
+        Err(GeneveErr::UnsupportedVersion(1))
+    ));
+    assert!(matches!(
+        Header::unmarshal(&[0x40, 0x00, 0x65, 0x58, 0x00, 0xaa, 0xaa, 0x00]),
+        Err(GeneveErr::UnsupportedVersion(1))
+    ));
+    let hdr = Header {
+        version: 1,
+        ..Header::default()
+    };
+    assert!(matches!(
+        hdr.validate(),
+        Err(GeneveErr::UnsupportedVersion(1))
+    ));
+}
+
+#[test]
+fn geneve_header_count_options() {
+    let encoded: [u8; 24] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+    ];
+    assert_eq!(Header::count_options(&encoded), Some(2));
+    assert_eq!(Header::count_options(&encoded[..8]), None);
+    assert_eq!(Header::count_options(&encoded[..20]), None);
+    assert_eq!(
+        Header::count_options(&[0x00, 0x00, 0x65, 0x58, 0x00, 0xaa, 0xaa, 0x00]),
+        Some(0)
+    );
+    // Second option declares 8 data bytes but only 4 are left in the region
+    let mut overrun = encoded;
+    overrun[19] = 0x02;
+    assert_eq!(Header::count_options(&overrun), None);
+}
+
+#[test]
+fn geneve_header_raw_version() {
+    for version in 0..4 {
+        let first = (version << 6) | 0x3f;
+        assert_eq!(Header::raw_version(&[first]), Some(version));
+        assert_eq!(
+            Header::raw_version(&[first, 0x00, 0x65, 0x58, 0x00, 0xaa, 0xaa, 0x00]),
+            Some(version)
+        );
+    }
+    assert_eq!(Header::raw_version(&[]), None);
+    // Reported even where parsing rejects the version
+    let encoded = [0x80, 0x00, 0x65, 0x58, 0x00, 0xaa, 0xaa, 0x00];
+    assert!(Header::unmarshal(&encoded).is_err());
+    assert_eq!(Header::raw_version(&encoded), Some(2));
+}
+
+#[test]
+fn geneve_header_version_of() {
+    assert!(!is_minimum_length(7));
+    assert!(is_minimum_length(8));
+    const { assert!(is_minimum_length(MIN_GENEVE_HDR)) };
+    let encoded: [u8; 8] = [0x80, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00];
+    assert_eq!(Header::version_of(&encoded), Some(2));
+    assert_eq!(Header::version_of(&encoded[..7]), None);
+    assert_eq!(Header::version_of(&[0x3f; 8]), Some(0));
+}
+
+#[test]
+fn geneve_header_unmarshal_into() {
+    let encoded: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let empty = TunnelOption::new(0, 0, false, None).unwrap();
+    let mut opts = [empty.clone(), empty.clone(), empty.clone(), empty.clone()];
+    let (hdr, cursor, count) = Header::unmarshal_into(&encoded, &mut opts).unwrap();
+    let (expected, _) = Header::unmarshal(&encoded).unwrap();
+    assert_eq!(cursor, 24);
+    assert_eq!(count, 2);
+    assert!(hdr.options.is_none());
+    assert_eq!(hdr.vni, expected.vni);
+    assert_eq!(opts[..count], expected.options()[..]);
+    assert_eq!(opts[2], empty);
+    let mut short = [empty.clone()];
+    assert!(matches!(
+        Header::unmarshal_into(&encoded, &mut short),
+        Err(GeneveErr::OptionSlotsTooFew { needed: 2, got: 1 })
+    ));
+    assert!(matches!(
+        Header::unmarshal_into(&encoded[..20], &mut opts),
+        Err(GeneveErr::BufferTooShort {
+            needed: 24,
+            got: 20
+        })
+    ));
+}
+
+#[test]
+fn geneve_header_unmarshal_errors() {
+    assert!(matches!(
+        Header::unmarshal(&[0x00, 0x00, 0x65, 0x58]),
+        Err(GeneveErr::BufferTooShort { needed: 8, got: 4 })
+    ));
+    assert!(matches!(
+        Header::unmarshal(&[0xc0, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00]),
+        Err(GeneveErr::UnsupportedVersion(3))
+    ));
+    assert!(matches!(
+        Header::unmarshal(&[0x00, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00]),
+        Ok((_, 8))
+    ));
+}
+
+#[test]
+fn geneve_header_unmarshal_truncated_options() {
+    // Declares 16 bytes of options but only 8 are present
+    let encoded: [u8; 16] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00,
+    ];
+    assert!(matches!(
+        Header::unmarshal(&encoded),
+        Err(GeneveErr::BufferTooShort {
+            needed: 24,
+            got: 16
+        })
+    ));
+    assert!(matches!(
+        GenevePacket::unmarshal(&encoded),
+        Err(GeneveErr::BufferTooShort {
+            needed: 24,
+            got: 16
+        })
+    ));
+    assert!(GenevePacket::try_from(&encoded[..]).is_err());
+}
+
+#[test]
+fn geneve_header_marshal() {
+    let decoded = Header {
+        version: 0,
+        control_flag: false,
+        critical_flag: false,
+        reserved1: 0,
+        protocol: 0x86dd,
+        vni: 0x00aaaaee,
+        reserved2: 0,
+        options: Some(vec![
+            TunnelOption {
+                option_class: 0xffff,
+                option_type: 0x0a,
+                c_flag: false,
+                data: Some(&[0x00, 0x01, 0x00, 0x00]),
+            },
+            TunnelOption {
+                option_class: 0xffff,
+                option_type: 0x0b,
+                c_flag: false,
+                data: Some(&[0x00, 0x02, 0x00, 0x00]),
+            },
+        ]),
+    };
+    let encoded: [u8; 24] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+    ];
+    let mut buffer: Vec<u8> = vec![];
+    decoded.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded);
+}
+
+#[test]
+fn geneve_header_marshal_to_slice() {
+    let encoded: [u8; 28] = [
+        0x05, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x02, 0x00, 0x01, 0x02,
+        0x03, 0x04, 0x05, 0x00, 0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+    ];
+    let decoded = HeaderBuilder::new()
+        .protocol(0x86dd)
+        .vni(0x00aaaaee)
+        .add_option(TunnelOption {
+            option_class: 0xffff,
+            option_type: 0x0a,
+            c_flag: false,
+            data: Some(&[0x00, 0x01, 0x02, 0x03, 0x04, 0x05]),
+        })
+        .add_option(TunnelOption {
+            option_class: 0xffff,
+            option_type: 0x0b,
+            c_flag: false,
+            data: Some(&[0x00, 0x02, 0x00, 0x00]),
+        })
+        .build()
+        .unwrap();
+    let mut vector: Vec<u8> = vec![];
+    decoded.marshal(&mut vector).unwrap();
+    assert_eq!(vector, encoded);
+    let mut slice = [0xee; 32];
+    assert_eq!(decoded.marshal_to_slice(&mut slice).unwrap(), 28);
+    assert_eq!(slice[..28], encoded);
+    assert!(matches!(
+        decoded.marshal_to_slice(&mut slice[..27]),
+        Err(GeneveErr::OutputTooSmall {
+            needed: 28,
+            got: 27
+        })
+    ));
+}
+
+#[test]
+fn geneve_header_estimate_marshal_len() {
+    let data = [0xab; 64];
+    let mut builder = HeaderBuilder::new().protocol(0x6558).vni(0x000001);
+    for i in 0..3 {
+        builder = builder.add_option(TunnelOption::new(0x0108, i, false, Some(&data)).unwrap());
+    }
+    let hdr = builder.build().unwrap();
+    assert_eq!(hdr.estimate_marshal_len(), hdr.header_len());
+    assert_eq!(hdr.estimate_marshal_len(), 8 + 3 * 68);
+    let mut buffer: Vec<u8> = Vec::new();
+    hdr.marshal(&mut buffer).unwrap();
+    assert!(buffer.capacity() >= hdr.header_len());
+    assert_eq!(buffer.len(), hdr.header_len());
+    let (parsed, _) = Header::unmarshal(&buffer).unwrap();
+    assert_eq!(parsed, hdr);
+}
+
+#[test]
+fn geneve_header_marshal_into() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let (hdr, _) = Header::unmarshal(&encoded_payload).unwrap();
+    let mut buffer = [0xee; 32];
+    let rest = hdr.marshal_into(&mut buffer).unwrap();
+    assert_eq!(rest.len(), 8);
+    rest[..6].copy_from_slice(&encoded_payload[24..]);
+    assert_eq!(buffer[..30], encoded_payload);
+    assert_eq!(buffer[30..], [0xee, 0xee]);
+    assert!(matches!(
+        hdr.marshal_into(&mut buffer[..23]),
+        Err(GeneveErr::OutputTooSmall {
+            needed: 24,
+            got: 23
+        })
+    ));
+    assert!(hdr.marshal_into(&mut buffer[..24]).unwrap().is_empty());
+}
+
+#[test]
+fn geneve_header_marshal_to_slice_too_small() {
+    let decoded = HeaderBuilder::new()
+        .protocol(0x6558)
+        .vni(0x000001)
+        .build()
+        .unwrap();
+    for len in [0, 4, 7] {
+        let mut buffer = vec![0; len];
+        assert!(matches!(
+            decoded.marshal_to_slice(&mut buffer),
+            Err(GeneveErr::OutputTooSmall { needed: 8, got }) if got == len
+        ));
+    }
+    let mut buffer = [0; 8];
+    assert_eq!(decoded.marshal_to_slice(&mut buffer).unwrap(), 8);
+    let option = TunnelOption::new(0x0108, 0x01, false, Some(&[0x00, 0x01])).unwrap();
+    let mut buffer = [0; 4];
+    assert!(matches!(
+        option.marshal_to_slice(&mut buffer),
+        Err(GeneveErr::OutputTooSmall { needed: 8, got: 4 })
+    ));
+}
+
+#[test]
+fn geneve_header_marshal_error_leaves_buffer() {
+    let data = [0xab; 128];
+    let decoded = HeaderBuilder::new()
+        .add_option(TunnelOption {
+            option_class: 0xffff,
+            option_type: 0x0a,
+            c_flag: false,
+            data: Some(&[0x00, 0x01, 0x00, 0x00]),
+        })
+        .add_option(TunnelOption {
+            option_class: 0xffff,
+            option_type: 0x0b,
+            c_flag: false,
+            data: Some(&data),
+        })
+        .build()
+        .unwrap();
+    let mut buffer: Vec<u8> = vec![0x01, 0x02];
+    assert!(matches!(
+        decoded.marshal(&mut buffer),
+        Err(GeneveErr::OptionTooLong)
+    ));
+    assert_eq!(buffer, [0x01, 0x02]);
+}
+
+#[test]
+fn geneve_header_options_too_long() {
+    let data = [0xab; MAX_DATA_SIZE];
+    let option = TunnelOption::new(0x0108, 0x01, false, Some(&data)).unwrap();
+    assert!(matches!(
+        HeaderBuilder::new()
+            .add_option(option.clone())
+            .add_option(option.clone())
+            .build(),
+        Err(GeneveErr::OptionsTooLong)
+    ));
+    // Options pushed after build are checked again at marshal time
+    let mut decoded = HeaderBuilder::new()
+        .add_option(option.clone())
+        .build()
+        .unwrap();
+    decoded.options.as_mut().unwrap().push(option.clone());
+    // 256 bytes of options do not fit the 6-bit options length field
+    assert_eq!(decoded.options_total_len(), 256);
+    assert!(decoded.options_total_len() > Header::MAX_OPTIONS_LEN);
+    let mut buffer: Vec<u8> = vec![0x01, 0x02];
+    assert!(matches!(
+        decoded.marshal(&mut buffer),
+        Err(GeneveErr::OptionsTooLong)
+    ));
+    assert_eq!(buffer, [0x01, 0x02]);
+    let mut slice = [0; 512];
+    assert!(matches!(
+        decoded.marshal_to_slice(&mut slice),
+        Err(GeneveErr::OptionsTooLong)
+    ));
+    let fits = HeaderBuilder::new().add_option(option).build().unwrap();
+    assert_eq!(fits.marshal_to_slice(&mut slice).unwrap(), 136);
+}
+
+#[test]
+fn geneve_header_reserved_bits() {
+    let encoded: [u8; 16] = [
+        0x02, 0x95, 0x65, 0x58, 0x00, 0x00, 0x01, 0x7e, 0x01, 0x08, 0x01, 0x01, 0x00, 0x00, 0x00,
+        0x01,
+    ];
+    let (hdr, _) = Header::unmarshal(&encoded).unwrap();
+    assert!(hdr.control_flag);
+    assert!(!hdr.critical_flag);
+    assert_eq!(hdr.reserved1, 0x15);
+    assert_eq!(hdr.reserved2, 0x7e);
+    let mut buffer: Vec<u8> = vec![];
+    hdr.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded);
+}
+
+#[test]
+fn geneve_header_unmarshal_strict() {
+    let encoded: [u8; 16] = [
+        0x02, 0x95, 0x65, 0x58, 0x00, 0x00, 0x01, 0x7e, 0x01, 0x08, 0x01, 0x01, 0x00, 0x00, 0x00,
+        0x01,
+    ];
+    assert!(Header::unmarshal(&encoded).is_ok());
+    assert!(matches!(
+        Header::unmarshal_strict(&encoded),
+        Err(GeneveErr::ReservedBitsSet)
+    ));
+    let mut clean = encoded;
+    clean[1] = 0x80;
+    clean[7] = 0x00;
+    assert!(matches!(Header::unmarshal_strict(&clean), Ok((_, 16))));
+    clean[2..4].copy_from_slice(&[0x00, 0x00]);
+    assert!(Header::unmarshal(&clean).is_ok());
+    assert!(matches!(
+        Header::unmarshal_strict(&clean),
+        Err(GeneveErr::InvalidProtocol)
+    ));
+    // Version and options length checks are shared with the lenient parse
+    assert!(matches!(
+        Header::unmarshal_strict(&[0x40, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00]),
+        Err(GeneveErr::UnsupportedVersion(1))
+    ));
+    assert!(matches!(
+        Header::unmarshal_strict(&encoded[..12]),
+        Err(GeneveErr::BufferTooShort {
+            needed: 16,
+            got: 12
+        })
+    ));
+}
+
+#[test]
+fn geneve_header_fixed_header_bytes() {
+    let encoded: [u8; 24] = [
+        0x04, 0x40, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x8a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+    ];
+    let (hdr, _) = Header::unmarshal(&encoded).unwrap();
+    let mut buffer: Vec<u8> = vec![];
+    hdr.marshal(&mut buffer).unwrap();
+    assert_eq!(hdr.fixed_header_bytes(), buffer[..MIN_GENEVE_HDR]);
+    assert_eq!(hdr.fixed_header_bytes(), encoded[..MIN_GENEVE_HDR]);
+}
+
+#[test]
+fn geneve_header_fixed() {
+    let encoded: [u8; 24] = [
+        0x04, 0x40, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x8a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+    ];
+    let (hdr, cursor) = Header::unmarshal(&encoded).unwrap();
+    let (fixed, opts_end) = FixedHeader::unmarshal(&encoded).unwrap();
+    assert_eq!(hdr.fixed(), fixed);
+    assert_eq!(opts_end, cursor);
+    assert_eq!(
+        fixed.encode(hdr.options_total_len()),
+        hdr.fixed_header_bytes()
+    );
+    let mut buffer = [0; 24];
+    assert_eq!(
+        fixed.marshal_to_slice(hdr.options(), &mut buffer).unwrap(),
+        24
+    );
+    assert_eq!(buffer, encoded);
+    assert_eq!(Header::from_fixed(fixed, hdr.options.clone().unwrap()), hdr);
+    assert_eq!(Header::from_fixed(fixed, vec![]).options, None);
+}
+
+#[test]
+fn geneve_header_to_buf() {
+    let encoded: [u8; 24] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+    ];
+    let (hdr, _) = Header::unmarshal(&encoded).unwrap();
+    let owned = hdr.to_buf();
+    assert_eq!(owned.as_header(), hdr);
+    // The std `ToOwned` is no longer shadowed and clones the borrowed header
+    let cloned: Header = hdr.to_owned();
+    assert_eq!(cloned, hdr);
+    let option = &hdr.options()[0];
+    assert_eq!(option.to_buf().as_option(), *option);
+    let options = owned.options.as_ref().unwrap();
+    assert_eq!(options[1].data, Some(vec![0x00, 0x02, 0x00, 0x00]));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn geneve_header_serde_json() {
+    let encoded: [u8; 24] = [
+        0x04, 0x40, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x8a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+    ];
+    let (hdr, _) = Header::unmarshal(&encoded).unwrap();
+    let json = serde_json::to_string(&hdr).unwrap();
+    assert!(json.contains("\"data\":[0,1,0,0]"));
+    let decoded: HeaderBuf = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.as_header(), hdr);
+    let mut buffer: Vec<u8> = vec![];
+    decoded.as_header().marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded);
+}
+
+#[test]
+fn geneve_header_unmarshal() {
+    let decoded = Header {
+        version: 0,
+        control_flag: false,
         critical_flag: false,
+        reserved1: 0,
+        protocol: 0x86dd,
+        vni: 0x00aaaaee,
+        reserved2: 0,
+        options: Some(vec![
+            TunnelOption {
+                option_class: 0xffff,
+                option_type: 0x0a,
+                c_flag: false,
+                data: Some(&[0x00, 0x01, 0x00, 0x00]),
+            },
+            TunnelOption {
+                option_class: 0xffff,
+                option_type: 0x0b,
+                c_flag: false,
+                data: Some(&[0x00, 0x02, 0x00, 0x00]),
+            },
+        ]),
+    };
+    let encoded: [u8; 24] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+    ];
+    if let Ok((i, _)) = Header::unmarshal(&encoded) {
+        assert_eq!(i, decoded);
+    }
+}
+
+#[test]
+fn geneve_header_set_protocol_type() {
+    let mut hdr = HeaderBuilder::new().vni(0x000001).build().unwrap();
+    assert!(matches!(hdr.validate(), Err(GeneveErr::InvalidProtocol)));
+    // Lenient by default
+    let mut buffer: Vec<u8> = vec![];
+    hdr.marshal(&mut buffer).unwrap();
+    hdr.set_protocol_type(ProtocolType::Ipv6);
+    assert_eq!(hdr.protocol, 0x86dd);
+    assert_eq!(hdr.protocol_type(), ProtocolType::Ipv6);
+    assert!(hdr.validate().is_ok());
+}
+
+#[test]
+fn geneve_header_default() {
+    let hdr = Header::default();
+    assert_eq!(hdr.header_len(), 8);
+    assert_eq!(hdr.protocol_type(), ProtocolType::Ethernet);
+    let mut buffer: Vec<u8> = vec![];
+    hdr.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, [0x00, 0x00, 0x65, 0x58, 0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(Header::unmarshal_strict(&buffer).unwrap().0, hdr);
+    let updated = Header {
+        vni: 0x000001,
+        ..Header::default()
+    };
+    assert_eq!(updated.fixed_header_bytes()[4..7], [0x00, 0x00, 0x01]);
+}
+
+#[test]
+fn geneve_header_protocol_checks() {
+    for (protocol, ethernet, ip) in [
+        (0x6558, true, false),
+        (0x0800, false, true),
+        (0x86dd, false, true),
+        (0x0806, false, false),
+        (0x1234, false, false),
+    ] {
+        let hdr = HeaderBuilder::new().protocol(protocol).build().unwrap();
+        assert_eq!(hdr.protocol_is_ethernet(), ethernet);
+        assert_eq!(hdr.protocol_is_ip(), ip);
+    }
+}
+
+#[test]
+fn geneve_header_options() {
+    let empty = HeaderBuilder::new().build().unwrap();
+    assert!(empty.options.is_none());
+    assert!(empty.options().is_empty());
+    let encoded: [u8; 24] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+    ];
+    let packet = GenevePacket::unmarshal(&encoded).unwrap();
+    assert_eq!(packet.hdr.options().len(), 2);
+    assert_eq!(packet.options()[1].option_type, 0x0b);
+}
+
+#[test]
+fn geneve_header_raw_options() {
+    let encoded: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let raw = Header::raw_options(&encoded).unwrap();
+    assert_eq!(raw.len(), 16);
+    assert_eq!(raw, &encoded[8..24]);
+    assert_eq!(
+        Header::raw_options(&[0x00, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00]),
+        Some(&[][..])
+    );
+    assert!(Header::raw_options(&encoded[..20]).is_none());
+    assert!(Header::raw_options(&encoded[..4]).is_none());
+}
+
+#[test]
+fn geneve_header_try_from() {
+    let encoded: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let (expected, _) = Header::unmarshal(&encoded).unwrap();
+    assert_eq!(Header::try_from(&encoded[..]).unwrap(), expected);
+    assert!(matches!(
+        Header::try_from(&encoded[..20]),
+        Err(GeneveErr::BufferTooShort {
+            needed: 24,
+            got: 20
+        })
+    ));
+    assert!(matches!(
+        Header::try_from(&[0x40, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00][..]),
+        Err(GeneveErr::UnsupportedVersion(1))
+    ));
+}
+
+#[test]
+fn geneve_header_builder() {
+    let expected = Header {
+        version: 0,
+        control_flag: false,
+        critical_flag: true,
+        reserved1: 0,
         protocol: 0x86dd,
         vni: 0x00aaaaee,
+        reserved2: 0,
         options: Some(vec![
             TunnelOption {
                 option_class: 0xffff,
                 option_type: 0x0a,
-                c_flag: false,
-                data: Some(vec![0x00, 0x01, 0x00, 0x00]),
+                c_flag: true,
+                data: Some(&[0x00, 0x01, 0x00, 0x00]),
             },
             TunnelOption {
                 option_class: 0xffff,
                 option_type: 0x0b,
                 c_flag: false,
-                data: Some(vec![0x00, 0x02, 0x00, 0x00]),
+                data: Some(&[0x00, 0x02, 0x00, 0x00]),
             },
         ]),
-        options_len: 16,
     };
+    let built = HeaderBuilder::new()
+        .version(0)
+        .protocol(0x86dd)
+        .vni(0x00aaaaee)
+        .control_flag(false)
+        .critical_flag(true)
+        .add_option(TunnelOption {
+            option_class: 0xffff,
+            option_type: 0x0a,
+            c_flag: true,
+            data: Some(&[0x00, 0x01, 0x00, 0x00]),
+        })
+        .add_option(TunnelOption {
+            option_class: 0xffff,
+            option_type: 0x0b,
+            c_flag: false,
+            data: Some(&[0x00, 0x02, 0x00, 0x00]),
+        })
+        .build()
+        .unwrap();
+    assert_eq!(built, expected);
+    assert_eq!(built.header_len(), 24);
+    assert_eq!(built.header_len(), expected.header_len());
+    let mut built_buffer: Vec<u8> = vec![];
+    let mut expected_buffer: Vec<u8> = vec![];
+    built.marshal(&mut built_buffer).unwrap();
+    expected.marshal(&mut expected_buffer).unwrap();
+    assert_eq!(built_buffer, expected_buffer);
+    match Header::unmarshal(&built_buffer) {
+        Ok((i, _)) => assert_eq!(i, built),
+        Err(_) => panic!(),
+    }
+}
+
+#[test]
+fn geneve_header_options_len() {
     let encoded: [u8; 24] = [
         0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
         0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
     ];
-    if let Some((i, _)) = Header::unmarshal(&encoded) {
-        assert_eq!(i, decoded);
+    let (mut hdr, _) = Header::unmarshal(&encoded).unwrap();
+    assert_eq!(hdr.options_len(), 16);
+    if let Some(i) = &mut hdr.options {
+        i.push(TunnelOption {
+            option_class: 0xffff,
+            option_type: 0x0c,
+            c_flag: false,
+            data: Some(&[0x00, 0x03]),
+        });
+    }
+    assert_eq!(hdr.options_len(), 24);
+    hdr.options = None;
+    assert_eq!(hdr.options_len(), 0);
+    // Over the encodable maximum and past what a u8 holds, reported without wrapping
+    let data = [0xaa; MAX_DATA_SIZE];
+    let option = TunnelOption::new(0x0101, 0x01, false, Some(&data)).unwrap();
+    hdr.options = Some(vec![option; 3]);
+    assert_eq!(hdr.options_len(), 3 * (MIN_OPT_SIZE + MAX_DATA_SIZE));
+    assert!(hdr.options_len() > 255);
+    assert!(matches!(
+        hdr.marshal(&mut vec![]),
+        Err(GeneveErr::OptionsTooLong)
+    ));
+}
+
+#[test]
+fn geneve_header_find_option() {
+    let hdr = HeaderBuilder::new()
+        .protocol(0x6558)
+        .vni(0x000001)
+        .add_option(TunnelOption {
+            option_class: 0x0108,
+            option_type: 0x01,
+            c_flag: false,
+            data: Some(&[0x00, 0x00, 0x00, 0x01]),
+        })
+        .add_option(TunnelOption {
+            option_class: 0x0104,
+            option_type: 0x01,
+            c_flag: false,
+            data: Some(&[0x00, 0x00, 0x00, 0x02]),
+        })
+        .add_option(TunnelOption {
+            option_class: 0x0108,
+            option_type: 0x01,
+            c_flag: false,
+            data: Some(&[0x00, 0x00, 0x00, 0x03]),
+        })
+        .build()
+        .unwrap();
+    assert_eq!(
+        hdr.find_option(0x0104, 0x01).and_then(|i| i.data),
+        Some(&[0x00, 0x00, 0x00, 0x02][..])
+    );
+    assert_eq!(
+        hdr.find_option(0x0108, 0x01).and_then(|i| i.data),
+        Some(&[0x00, 0x00, 0x00, 0x01][..])
+    );
+    assert_eq!(hdr.find_options(0x0108, 0x01).count(), 2);
+    assert!(hdr.find_option(0x0108, 0x02).is_none());
+    assert!(hdr.find_option(0x0110, 0x01).is_none());
+    let empty = HeaderBuilder::new().build().unwrap();
+    assert!(empty.find_option(0x0108, 0x01).is_none());
+    assert_eq!(empty.find_options(0x0108, 0x01).count(), 0);
+}
+
+#[test]
+fn geneve_header_options_by_class() {
+    let hdr = HeaderBuilder::new()
+        .protocol(0x6558)
+        .vni(0x000001)
+        .add_option(TunnelOption {
+            option_class: 0x0108,
+            option_type: 0x02,
+            c_flag: false,
+            data: Some(&[0x00, 0x00, 0x00, 0x01]),
+        })
+        .add_option(TunnelOption {
+            option_class: 0x0104,
+            option_type: 0x01,
+            c_flag: false,
+            data: None,
+        })
+        .add_option(TunnelOption {
+            option_class: 0x0108,
+            option_type: 0x01,
+            c_flag: true,
+            data: Some(&[0x00, 0x00, 0x00, 0x02]),
+        })
+        .build()
+        .unwrap();
+    let types: Vec<u8> = hdr
+        .options_by_class(0x0108)
+        .map(|i| i.option_type)
+        .collect();
+    assert_eq!(types, [0x02, 0x01]);
+    assert_eq!(hdr.options_by_class(0x0110).count(), 0);
+    let mut buffer: Vec<u8> = vec![];
+    hdr.marshal(&mut buffer).unwrap();
+    let (decoded, _) = Header::unmarshal(&buffer).unwrap();
+    assert_eq!(decoded, hdr);
+    let data: Vec<_> = decoded.options_by_class(0x0108).map(|i| i.data).collect();
+    assert_eq!(
+        data,
+        [
+            Some(&[0x00, 0x00, 0x00, 0x01][..]),
+            Some(&[0x00, 0x00, 0x00, 0x02][..])
+        ]
+    );
+}
+
+#[test]
+fn geneve_vni() {
+    assert!(matches!(Vni::new(0x01000000), Err(GeneveErr::InvalidVni)));
+    let vni = Vni::new(0x00ffffff).unwrap();
+    assert_eq!(vni.u32(), 0x00ffffff);
+    assert_eq!(u32::from(vni), 0x00ffffff);
+    let mut hdr = HeaderBuilder::new().build().unwrap();
+    hdr.set_vni(Vni::new(0x00aaaaee).unwrap());
+    assert_eq!(hdr.vni, 0x00aaaaee);
+    assert_eq!(hdr.fixed_header_bytes()[4..7], [0xaa, 0xaa, 0xee]);
+}
+
+#[test]
+fn geneve_header_remove_option() {
+    let encoded: [u8; 24] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+    ];
+    let (mut hdr, _) = Header::unmarshal(&encoded).unwrap();
+    assert!(!hdr.remove_option(0xffff, 0x0c));
+    assert!(hdr.remove_option(0xffff, 0x0a));
+    assert_eq!(hdr.options().len(), 1);
+    assert_eq!(hdr.options_len(), 8);
+    let mut buffer: Vec<u8> = vec![];
+    hdr.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer[0], 0x02);
+    assert_eq!(buffer[1..8], encoded[1..8]);
+    assert_eq!(buffer[8..], encoded[16..]);
+    assert!(hdr.remove_option(0xffff, 0x0b));
+    assert!(hdr.options.is_none());
+    assert!(!hdr.remove_option(0xffff, 0x0b));
+    let (mut hdr, _) = Header::unmarshal(&encoded).unwrap();
+    hdr.clear_options();
+    assert!(hdr.options.is_none());
+    buffer.clear();
+    hdr.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, [0x00, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00]);
+}
+
+#[test]
+fn geneve_protocol_type() {
+    let known = [
+        (0x6558, ProtocolType::Ethernet),
+        (0x0800, ProtocolType::Ipv4),
+        (0x0806, ProtocolType::Arp),
+        (0x86dd, ProtocolType::Ipv6),
+        (0x8847, ProtocolType::Mpls),
+        (0x894f, ProtocolType::Nsh),
+    ];
+    for (raw, protocol) in known {
+        assert_eq!(ProtocolType::from(raw), protocol);
+        assert_eq!(u16::from(protocol), raw);
+    }
+    assert_eq!(ProtocolType::from(0x1234), ProtocolType::Unknown(0x1234));
+    let raw: u16 = ProtocolType::Unknown(0x1234).into();
+    assert_eq!(raw, 0x1234);
+    let hdr = HeaderBuilder::new().protocol(0x86dd).build().unwrap();
+    assert_eq!(hdr.protocol_type(), ProtocolType::Ipv6);
+    assert_eq!(hdr.protocol, 0x86dd);
+}
+
+#[test]
+fn geneve_option_class() {
+    let known = [
+        (0x0100, OptionClass::Linux),
+        (0x0101, OptionClass::OpenVSwitch),
+        (0x0102, OptionClass::Ovn),
+        (0x0103, OptionClass::Int),
+        (0x0104, OptionClass::Vmware),
+        (0x0105, OptionClass::Amazon(0x0105)),
+        (0x0106, OptionClass::Cisco(0x0106)),
+        (0x0107, OptionClass::Oracle),
+        (0x0108, OptionClass::Amazon(0x0108)),
+        (0x0110, OptionClass::Amazon(0x0110)),
+        (0x0111, OptionClass::Ibm(0x0111)),
+        (0x0118, OptionClass::Ibm(0x0118)),
+        (0x0119, OptionClass::Ericsson(0x0119)),
+        (0x0128, OptionClass::Ericsson(0x0128)),
+        (0x0129, OptionClass::Oxide),
+        (0xff00, OptionClass::Experimental(0xff00)),
+        (0xfff0, OptionClass::Experimental(0xfff0)),
+        (0xffff, OptionClass::Experimental(0xffff)),
+    ];
+    for (raw, class) in known {
+        assert_eq!(OptionClass::from(raw), class);
+        assert_eq!(u16::from(class), raw);
+    }
+    assert_eq!(OptionClass::from(0x1234), OptionClass::Unknown(0x1234));
+    // Unassigned classes, including the ends of the IETF Review range and those just
+    // outside the registered and experimental ranges
+    for raw in [0x0000, 0x00ff, 0x012a, 0xfeff] {
+        assert_eq!(OptionClass::from(raw), OptionClass::Unknown(raw));
+    }
+    let raw: u16 = OptionClass::Unknown(0x1234).into();
+    assert_eq!(raw, 0x1234);
+    let option = TunnelOption::new(0x0108, 0x01, false, None).unwrap();
+    assert_eq!(option.class(), OptionClass::Amazon(0x0108));
+    assert_eq!(option.option_class, 0x0108);
+}
+
+#[test]
+fn geneve_header_option_mut() {
+    let encoded: [u8; 24] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+    ];
+    let data = [0x01, 0x02, 0x03, 0x04, 0x05];
+    let (mut hdr, _) = Header::unmarshal(&encoded).unwrap();
+    match hdr.option_mut(0xffff, 0x0a) {
+        Some(i) => i.data = Some(&data),
+        None => panic!(),
+    }
+    assert!(hdr.option_mut(0xffff, 0x0c).is_none());
+    assert_eq!(hdr.options_len(), 20);
+    let mut buffer: Vec<u8> = vec![];
+    hdr.marshal(&mut buffer).unwrap();
+    assert_eq!(
+        buffer,
+        [
+            0x05, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x02, 0x01, 0x02,
+            0x03, 0x04, 0x05, 0x00, 0x00, 0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+        ]
+    );
+}
+
+#[test]
+fn geneve_header_builder_invalid_vni() {
+    assert!(matches!(
+        HeaderBuilder::new().vni(0x01000000).build(),
+        Err(GeneveErr::InvalidVni)
+    ));
+    assert!(HeaderBuilder::new().vni(0x00ffffff).build().is_ok());
+}
+
+#[test]
+fn geneve_header_builder_unsupported_version() {
+    for version in [1, 3, 4, 0xff] {
+        assert!(matches!(
+            HeaderBuilder::new().version(version).build(),
+            Err(GeneveErr::UnsupportedVersion(i)) if i == version
+        ));
     }
+    assert_eq!(HeaderBuilder::new().version(0).build().unwrap().version, 0);
 }
 
 #[test]
@@ -323,23 +3210,24 @@ fn geneve_packet_unmarshal() {
         version: 0,
         control_flag: false,
         critical_flag: false,
+        reserved1: 0,
         protocol: 0x86dd,
         vni: 0x00aaaaee,
+        reserved2: 0,
         options: Some(vec![
             TunnelOption {
                 option_class: 0xffff,
                 option_type: 0x0a,
                 c_flag: false,
-                data: Some(vec![0x00, 0x01, 0x00, 0x00]),
+                data: Some(&[0x00, 0x01, 0x00, 0x00]),
             },
             TunnelOption {
                 option_class: 0xffff,
                 option_type: 0x0b,
                 c_flag: false,
-                data: Some(vec![0x00, 0x02, 0x00, 0x00]),
+                data: Some(&[0x00, 0x02, 0x00, 0x00]),
             },
         ]),
-        options_len: 16,
     };
     match GenevePacket::try_from(&encoded_payload[..]) {
         Ok(i) => assert_eq!(i.hdr, decoded_hdr),
@@ -347,6 +3235,510 @@ fn geneve_packet_unmarshal() {
     }
 }
 
+#[test]
+fn geneve_packet_entry_points() {
+    let short = [0x00, 0x00, 0x65];
+    assert!(GenevePacket::new(&short).is_none());
+    assert!(matches!(
+        GenevePacket::try_from(&short[..]),
+        Err(GeneveErr::BufferTooShort { needed: 8, got: 3 })
+    ));
+    assert!(matches!(
+        GenevePacket::unmarshal(&short),
+        Err(GeneveErr::BufferTooShort { needed: 8, got: 3 })
+    ));
+    let encoded: [u8; 10] = [0x00, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00, 0xde, 0xad];
+    let packet = GenevePacket::unmarshal(&encoded).unwrap();
+    assert_eq!(GenevePacket::new(&encoded), Some(packet.clone()));
+    assert_eq!(GenevePacket::try_from(&encoded[..]).unwrap(), packet);
+}
+
+#[test]
+fn geneve_packet_is_oam() {
+    let oam: [u8; 10] = [0x00, 0x80, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00, 0xde, 0xad];
+    let packet = GenevePacket::unmarshal(&oam).unwrap();
+    assert!(packet.is_oam());
+    assert!(packet.is_control());
+    let hdr = Header {
+        control_flag: false,
+        ..Header::default()
+    };
+    let packet = GenevePacket::from_parts(hdr, &[0xde, 0xad]);
+    assert!(!packet.is_oam());
+    assert!(!packet.is_control());
+}
+
+#[test]
+fn geneve_packet_diagnose() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    assert!(GenevePacket::diagnose(&encoded_payload).is_empty());
+    assert!(matches!(
+        GenevePacket::diagnose(&encoded_payload[..4])[..],
+        [GeneveErr::BufferTooShort { needed: 8, got: 4 }]
+    ));
+    // Version 1, reserved bits set, protocol 0 and options declared past the end, the
+    // trailing option is cut off mid-data
+    let broken: [u8; 14] = [
+        0x45, 0x01, 0x00, 0x00, 0xaa, 0xaa, 0xee, 0x7e, 0xff, 0xff, 0x0a, 0x02, 0x00, 0x01,
+    ];
+    let issues = GenevePacket::diagnose(&broken);
+    assert!(matches!(
+        issues[..],
+        [
+            GeneveErr::UnsupportedVersion(1),
+            GeneveErr::ReservedBitsSet,
+            GeneveErr::InvalidProtocol,
+            GeneveErr::BufferTooShort {
+                needed: 28,
+                got: 14
+            },
+            GeneveErr::TruncatedOption,
+        ]
+    ));
+    // The fast path still stops at the first problem
+    assert!(matches!(
+        GenevePacket::unmarshal(&broken),
+        Err(GeneveErr::UnsupportedVersion(1))
+    ));
+}
+
+#[test]
+fn geneve_packet_parse() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let (packet, consumed) = GenevePacket::parse(&encoded_payload).unwrap();
+    assert_eq!(consumed, 24);
+    assert_eq!(consumed, packet.hdr.header_len());
+    assert_eq!(&encoded_payload[consumed..], packet.inner_payload());
+    assert!(matches!(
+        GenevePacket::parse(&encoded_payload[..20]),
+        Err(GeneveErr::BufferTooShort {
+            needed: 24,
+            got: 20
+        })
+    ));
+}
+
+#[test]
+fn geneve_packet_inner_ip_version() {
+    let mut encoded: Vec<u8> = vec![0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x01, 0x00];
+    encoded.extend_from_slice(&[0x45, 0x00, 0x00, 0x14]);
+    let packet = GenevePacket::unmarshal(&encoded).unwrap();
+    assert_eq!(packet.inner_ip_version(), Some(4));
+    assert_eq!(
+        GenevePacket::unmarshal(&encoded[..8])
+            .unwrap()
+            .inner_ip_version(),
+        None
+    );
+
+    let mut encoded: Vec<u8> = vec![0x00, 0x00, 0x86, 0xdd, 0x00, 0x00, 0x01, 0x00];
+    encoded.extend_from_slice(&[0x60, 0x00, 0x00, 0x00]);
+    let packet = GenevePacket::unmarshal(&encoded).unwrap();
+    assert_eq!(packet.inner_ip_version(), Some(6));
+
+    // Ethernet payloads start with a MAC address, not a version nibble
+    encoded[2..4].copy_from_slice(&[0x65, 0x58]);
+    let packet = GenevePacket::unmarshal(&encoded).unwrap();
+    assert_eq!(packet.inner_ip_version(), None);
+}
+
+#[test]
+fn geneve_packet_to_static() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let source = encoded_payload.to_vec();
+    let owned = GenevePacket::unmarshal(&source).unwrap().to_static();
+    drop(source);
+    fn assert_static<T: 'static>(_: &T) {}
+    assert_static(&owned);
+    let mut buffer: Vec<u8> = vec![];
+    owned.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded_payload);
+}
+
+#[test]
+fn geneve_packet_header_payload_bytes() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let packet = GenevePacket::unmarshal(&encoded_payload).unwrap();
+    assert_eq!(packet.header_bytes(), &encoded_payload[..24]);
+    assert_eq!(packet.payload_bytes(), packet.inner_payload());
+    assert_eq!(
+        [packet.header_bytes(), packet.payload_bytes()].concat(),
+        encoded_payload
+    );
+    let built = GenevePacket::from_parts(packet.hdr.clone(), &encoded_payload[24..]);
+    assert!(built.header_bytes().is_empty());
+}
+
+#[test]
+fn geneve_packet_replace_payload() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let new_payload = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+    let mut packet = GenevePacket::unmarshal(&encoded_payload).unwrap();
+    packet.replace_payload(&new_payload);
+    assert_eq!(packet.inner_payload(), new_payload);
+    assert_eq!(packet.payload_offset(), 0);
+    assert_eq!(packet.total_len(), 32);
+    let mut buffer: Vec<u8> = vec![];
+    packet.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer[..24], encoded_payload[..24]);
+    assert_eq!(buffer[24..], new_payload);
+}
+
+#[test]
+fn geneve_packet_insert_option() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let mut packet = GenevePacket::unmarshal(&encoded_payload).unwrap();
+    packet
+        .insert_option(TunnelOption::new(0x0108, 0x01, false, Some(&[0x01, 0x02, 0x03])).unwrap())
+        .unwrap();
+    assert_eq!(packet.options().len(), 3);
+    let mut buffer: Vec<u8> = vec![];
+    packet.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer[0] & 0x3f, 6);
+    assert_eq!(
+        buffer[24..32],
+        [0x01, 0x08, 0x01, 0x01, 0x01, 0x02, 0x03, 0x00]
+    );
+    assert_eq!(buffer[32..], encoded_payload[24..]);
+    let reparsed = GenevePacket::unmarshal(&buffer).unwrap();
+    assert!(reparsed.options()[2].eq_semantic(&packet.options()[2]));
+    assert_eq!(reparsed.inner_payload(), packet.inner_payload());
+
+    // 24 + 128 + 100 bytes fill the options region exactly, another 8 bytes do not fit
+    let data = [0; MAX_DATA_SIZE];
+    packet
+        .insert_option(TunnelOption::new(0x0108, 0x02, false, Some(&data)).unwrap())
+        .unwrap();
+    packet
+        .insert_option(TunnelOption::new(0x0108, 0x03, false, Some(&data[..96])).unwrap())
+        .unwrap();
+    assert_eq!(packet.hdr.options_total_len(), MAX_OPTIONS_LEN);
+    assert!(matches!(
+        packet.insert_option(TunnelOption::new(0x0108, 0x04, false, Some(&[0x01])).unwrap()),
+        Err(GeneveErr::OptionsTooLong)
+    ));
+    assert_eq!(packet.options().len(), 5);
+}
+
+#[test]
+fn geneve_packet_unmarshal_at() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    // 14 bytes Ethernet, 20 bytes IPv4 and 8 bytes UDP ahead of the Geneve header
+    let mut frame = vec![0x45; 42];
+    frame.extend_from_slice(&encoded_payload);
+    let packet = GenevePacket::unmarshal_at(&frame, 42).unwrap();
+    assert_eq!(packet.hdr, Header::unmarshal(&encoded_payload).unwrap().0);
+    assert_eq!(packet.payload_offset(), 42 + 24);
+    assert_eq!(packet.payload, &frame[..]);
+    assert_eq!(packet.inner_payload(), &encoded_payload[24..]);
+    assert_eq!(packet.total_len(), 30);
+    let mut buffer: Vec<u8> = vec![];
+    packet.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded_payload);
+    assert!(matches!(
+        GenevePacket::unmarshal_at(&frame, 73),
+        Err(GeneveErr::BufferTooShort {
+            needed: 81,
+            got: 72
+        })
+    ));
+    // Offsets near usize::MAX saturate instead of overflowing
+    assert!(matches!(
+        GenevePacket::unmarshal_at(&frame, usize::MAX),
+        Err(GeneveErr::BufferTooShort {
+            needed: usize::MAX,
+            got: 72
+        })
+    ));
+    assert!(matches!(
+        GenevePacket::unmarshal_at(&frame, usize::MAX - 4),
+        Err(GeneveErr::BufferTooShort {
+            needed: usize::MAX,
+            got: 72
+        })
+    ));
+}
+
+#[test]
+fn geneve_packet_with_payload_len() {
+    // Inner frame of 4 bytes followed by 2 bytes of datagram padding
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let packet = GenevePacket::with_payload_len(&encoded_payload, 4).unwrap();
+    assert_eq!(packet.inner_payload(), [0xde, 0xad, 0xbe, 0xef]);
+    assert_eq!(packet.payload_offset(), 24);
+    assert_eq!(packet.total_len(), 28);
+    let mut buffer: Vec<u8> = vec![];
+    packet.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded_payload[..28]);
+    assert_eq!(
+        GenevePacket::with_payload_len(&encoded_payload, 6)
+            .unwrap()
+            .inner_payload()
+            .len(),
+        6
+    );
+    assert!(matches!(
+        GenevePacket::with_payload_len(&encoded_payload, 7),
+        Err(GeneveErr::BufferTooShort {
+            needed: 31,
+            got: 30
+        })
+    ));
+    assert!(matches!(
+        GenevePacket::with_payload_len(&encoded_payload, usize::MAX),
+        Err(GeneveErr::BufferTooShort {
+            needed: usize::MAX,
+            got: 30
+        })
+    ));
+}
+
+#[test]
+fn geneve_packet_payload_offset() {
+    let encoded_payload: [u8; 38] = [
+        0x06, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00, 0x01, 0x08, 0x01, 0x02, 0x00, 0x01, 0x02,
+        0x03, 0x04, 0x05, 0x00, 0x00, 0x01, 0x08, 0x02, 0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02,
+        0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let packet = GenevePacket::unmarshal(&encoded_payload).unwrap();
+    assert_eq!(packet.hdr.options.as_ref().map(|i| i.len()), Some(3));
+    assert_eq!(packet.payload_offset(), 32);
+    assert_eq!(packet.payload_offset(), packet.hdr.header_len());
+    assert_eq!(
+        &encoded_payload[packet.payload_offset()..],
+        packet.inner_payload()
+    );
+}
+
+#[test]
+fn geneve_packet_validate_critical() {
+    let encoded_payload: [u8; 28] = [
+        0x04, 0x40, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00, 0x01, 0x08, 0x81, 0x01, 0x00, 0x00, 0x00,
+        0x01, 0x01, 0x10, 0x85, 0x01, 0x00, 0x00, 0x00, 0x02, 0xde, 0xad, 0xbe, 0xef,
+    ];
+    let packet = GenevePacket::unmarshal(&encoded_payload).unwrap();
+    assert!(packet
+        .validate_critical(&[(0x0108, 0x01), (0x0110, 0x05)])
+        .is_ok());
+    assert!(matches!(
+        packet.validate_critical(&[(0x0108, 0x01)]),
+        Err(GeneveErr::UnknownCriticalOption {
+            class: 0x0110,
+            option_type: 0x05
+        })
+    ));
+    // Non-critical options may be ignored when unknown
+    let non_critical: [u8; 16] = [
+        0x02, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00, 0x01, 0x10, 0x05, 0x01, 0x00, 0x00, 0x00,
+        0x02,
+    ];
+    let packet = GenevePacket::unmarshal(&non_critical).unwrap();
+    assert!(packet.validate_critical(&[]).is_ok());
+}
+
+#[test]
+fn geneve_packet_validate_against() {
+    let encoded_payload: [u8; 32] = [
+        0x05, 0x40, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00, 0x01, 0x08, 0x81, 0x01, 0x00, 0x00, 0x00,
+        0x01, 0x01, 0x10, 0x85, 0x01, 0x00, 0x00, 0x00, 0x02, 0x01, 0x10, 0x06, 0x00, 0xde, 0xad,
+        0xbe, 0xef,
+    ];
+    let packet = GenevePacket::unmarshal(&encoded_payload).unwrap();
+    let mut registry = OptionRegistry::new();
+    registry.register(0x0108, 0x01).register(0x0104, 0x01);
+    assert!(registry.contains(0x0108, 0x01));
+    assert!(!registry.contains(0x0110, 0x05));
+    // The unknown non-critical 0x0110/0x06 option is not reported
+    match packet.validate_against(&registry) {
+        Err(GeneveErr::UnknownCriticalOptions(i)) => assert_eq!(i, [(0x0110, 0x05)]),
+        _ => panic!(),
+    }
+    registry.register(0x0110, 0x05);
+    assert!(packet.validate_against(&registry).is_ok());
+    let empty = OptionRegistry::new();
+    match packet.validate_against(&empty) {
+        Err(GeneveErr::UnknownCriticalOptions(i)) => {
+            assert_eq!(i, [(0x0108, 0x01), (0x0110, 0x05)])
+        }
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn geneve_packet_total_len() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let packet = GenevePacket::unmarshal(&encoded_payload).unwrap();
+    assert_eq!(packet.total_len(), 30);
+    assert_eq!(packet.hdr.total_len(6), 30);
+    let mut buffer = vec![0; packet.total_len()];
+    assert_eq!(
+        packet.marshal_to_slice(&mut buffer).unwrap(),
+        packet.total_len()
+    );
+    assert_eq!(buffer, encoded_payload);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn geneve_packet_write_to() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let packet = GenevePacket::unmarshal(&encoded_payload).unwrap();
+    let mut marshalled: Vec<u8> = vec![];
+    packet.marshal(&mut marshalled).unwrap();
+    let mut written: Vec<u8> = vec![];
+    assert_eq!(packet.write_to(&mut written).unwrap(), 30);
+    assert_eq!(written, marshalled);
+    let mut written: Vec<u8> = vec![];
+    assert_eq!(packet.hdr.write_to(&mut written).unwrap(), 24);
+    assert_eq!(written, marshalled[..24]);
+    let data = [0xab; 128];
+    let invalid = HeaderBuilder::new()
+        .add_option(TunnelOption {
+            option_class: 0x0108,
+            option_type: 0x01,
+            c_flag: false,
+            data: Some(&data),
+        })
+        .build()
+        .unwrap();
+    let mut written: Vec<u8> = vec![];
+    assert!(invalid.write_to(&mut written).is_err());
+    assert!(written.is_empty());
+}
+
+#[test]
+fn geneve_packet_peek() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let packet = GenevePacket::unmarshal(&encoded_payload).unwrap();
+    assert_eq!(
+        GenevePacket::peek(&encoded_payload).unwrap(),
+        packet.payload_offset()
+    );
+    assert!(matches!(
+        GenevePacket::peek(&encoded_payload[..20]),
+        Err(GeneveErr::BufferTooShort {
+            needed: 24,
+            got: 20
+        })
+    ));
+    assert!(matches!(
+        GenevePacket::peek(&encoded_payload[..4]),
+        Err(GeneveErr::BufferTooShort { needed: 8, got: 4 })
+    ));
+    assert!(matches!(
+        GenevePacket::peek(&[0x80, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00]),
+        Err(GeneveErr::UnsupportedVersion(2))
+    ));
+}
+
+#[test]
+fn geneve_packet_decapsulate() {
+    let encoded_payload: [u8; 22] = [
+        0x00, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02,
+        0x00, 0x00, 0x00, 0x00, 0x01, 0x08, 0x00,
+    ];
+    let packet = GenevePacket::unmarshal(&encoded_payload).unwrap();
+    let (protocol, frame) = packet.decapsulate();
+    assert_eq!(protocol, ProtocolType::Ethernet);
+    assert_eq!(frame, &encoded_payload[8..]);
+}
+
+#[test]
+fn geneve_packet_to_buf() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let source = encoded_payload.to_vec();
+    let owned = GenevePacket::unmarshal(&source).unwrap().to_buf();
+    drop(source);
+    assert_eq!(owned.payload, [0xde, 0xad, 0xbe, 0xef, 0x00, 0x00]);
+    let mut buffer: Vec<u8> = vec![];
+    owned.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded_payload);
+    let view = owned.as_packet();
+    assert_eq!(view.inner_payload(), owned.payload);
+    assert_eq!(view.hdr.to_buf(), owned.hdr);
+    let mut buffer: Vec<u8> = vec![];
+    view.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded_payload);
+}
+
+#[test]
+fn geneve_packet_inner_payload() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    match GenevePacket::try_from(&encoded_payload[..]) {
+        Ok(i) => assert_eq!(i.inner_payload(), [0x00; 6]),
+        Err(_) => panic!(),
+    }
+    let no_payload = &encoded_payload[..24];
+    match GenevePacket::try_from(no_payload) {
+        Ok(i) => assert!(i.inner_payload().is_empty()),
+        Err(_) => panic!(),
+    }
+}
+
+#[test]
+fn geneve_packet_clone() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let packet = GenevePacket::unmarshal(&encoded_payload).unwrap();
+    let mut copy = packet.clone();
+    assert_eq!(copy, packet);
+    copy.hdr.vni = 0x000001;
+    assert_ne!(copy, packet);
+    assert_eq!(packet.hdr.vni, 0x00aaaaee);
+}
+
+#[test]
+fn geneve_packet_unsupported_version() {
+    let encoded_payload: [u8; 14] = [
+        0x40, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    assert!(matches!(
+        GenevePacket::unmarshal(&encoded_payload),
+        Err(GeneveErr::UnsupportedVersion(1))
+    ));
+}
+
 #[test]
 fn geneve_packet_marshal() {
     let encoded_payload: [u8; 30] = [
@@ -355,7 +3747,247 @@ fn geneve_packet_marshal() {
     ];
     if let Some(packet) = GenevePacket::new(&encoded_payload) {
         let mut buffer = vec![];
-        packet.marshal(&mut buffer);
+        packet.marshal(&mut buffer).unwrap();
+        assert_eq!(buffer, encoded_payload);
+        let mut slice = [0; 30];
+        assert_eq!(packet.marshal_to_slice(&mut slice).unwrap(), 30);
+        assert_eq!(slice, encoded_payload);
+        assert!(matches!(
+            packet.marshal_to_slice(&mut slice[..29]),
+            Err(GeneveErr::OutputTooSmall {
+                needed: 30,
+                got: 29
+            })
+        ));
+    }
+}
+
+#[test]
+fn geneve_packet_builder() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let hdr = HeaderBuilder::new()
+        .protocol(0x86dd)
+        .vni(0x00aaaaee)
+        .add_option(
+            TunnelOption::new(0xffff, 0x0a, false, Some(&[0x00, 0x01, 0x00, 0x00])).unwrap(),
+        )
+        .add_option(
+            TunnelOption::new(0xffff, 0x0b, false, Some(&[0x00, 0x02, 0x00, 0x00])).unwrap(),
+        )
+        .build()
+        .unwrap();
+    let inner = [0x00; 6];
+    let builder = GenevePacket::builder(hdr).payload(&inner);
+    let mut buffer: Vec<u8> = vec![];
+    builder.build_into(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded_payload);
+    let mut slice = [0xee; 32];
+    assert_eq!(builder.build_to_slice(&mut slice).unwrap(), 30);
+    assert_eq!(slice[..30], encoded_payload);
+    assert!(matches!(
+        builder.build_to_slice(&mut slice[..29]),
+        Err(GeneveErr::OutputTooSmall {
+            needed: 30,
+            got: 29
+        })
+    ));
+    let packet = builder.build();
+    assert_eq!(packet.payload_offset(), 0);
+    assert_eq!(packet.inner_payload(), inner);
+    assert_eq!(packet.total_len(), 30);
+}
+
+#[test]
+fn geneve_packet_from_parts() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let parsed = GenevePacket::unmarshal(&encoded_payload).unwrap();
+    let built = GenevePacket::from_parts(parsed.hdr.clone(), &encoded_payload[24..]);
+    assert_eq!(parsed.payload_offset(), 24);
+    assert_eq!(built.payload_offset(), 0);
+    assert_eq!(built.payload, [0xde, 0xad, 0xbe, 0xef, 0x00, 0x00]);
+    for packet in [&parsed, &built] {
+        assert_eq!(packet.inner_payload(), [0xde, 0xad, 0xbe, 0xef, 0x00, 0x00]);
+        assert_eq!(packet.total_len(), 30);
+        let mut buffer: Vec<u8> = vec![];
+        packet.marshal(&mut buffer).unwrap();
         assert_eq!(buffer, encoded_payload);
+        let mut slice = [0xee; 32];
+        assert_eq!(packet.marshal_to_slice(&mut slice).unwrap(), 30);
+        assert_eq!(slice[..30], encoded_payload);
+    }
+}
+
+#[test]
+fn geneve_packet_lower_hex() {
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let packet = GenevePacket::unmarshal(&encoded_payload).unwrap();
+    let expected: String = encoded_payload
+        .iter()
+        .map(|i| format!("{:02x}", i))
+        .collect();
+    assert_eq!(format!("{:x}", packet), expected);
+    assert!(expected.starts_with("040086ddaaaaee00ffff0a01"));
+    assert!(expected.ends_with("deadbeef0000"));
+}
+
+#[test]
+fn geneve_header_into_iter() {
+    let encoded: [u8; 24] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00,
+    ];
+    let (hdr, _) = Header::unmarshal(&encoded).unwrap();
+    let mut total = 0;
+    for option in &hdr {
+        total += option.data_len();
+    }
+    assert_eq!(total, 8);
+    let mut count = 0;
+    for _ in &Header::default() {
+        count += 1;
+    }
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn geneve_header_display() {
+    let encoded: [u8; 24] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x8b, 0x01, 0x00, 0x02, 0x00, 0x00,
+    ];
+    let (hdr, _) = Header::unmarshal(&encoded).unwrap();
+    let pretty = format!("{}", hdr);
+    assert!(pretty.starts_with("Geneve v0 O=0 C=0 len=24\n"));
+    assert!(pretty.contains("protocol: 0x86dd (Ipv6)"));
+    assert!(pretty.contains("vni: 0xaaaaee"));
+    assert!(pretty.contains("option: class=0xffff type=0x0a c=0 len=4 data=00010000"));
+    assert!(pretty.contains("option: class=0xffff type=0x0b c=1 len=4 data=00020000"));
+    assert_eq!(pretty.lines().count(), 5);
+}
+
+#[test]
+fn geneve_header_oam_critical_bits() {
+    for (byte, oam, critical) in [
+        (0x00, false, false),
+        (0x80, true, false),
+        (0x40, false, true),
+        (0xc0, true, true),
+    ] {
+        let encoded: [u8; 8] = [0x00, byte, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00];
+        let (hdr, _) = Header::unmarshal(&encoded).unwrap();
+        assert_eq!(hdr.oam(), oam);
+        assert_eq!(hdr.oam(), hdr.control_flag);
+        assert_eq!(hdr.has_critical_options(), critical);
+        assert_eq!(hdr.has_critical_options(), hdr.critical_flag);
+    }
+}
+
+#[test]
+fn geneve_header_canonicalize() {
+    let first = TunnelOption::new(0x0108, 0x02, false, Some(&[0x00, 0x01])).unwrap();
+    let second = TunnelOption::new(0x0104, 0x01, false, None).unwrap();
+    let repeated = TunnelOption::new(0x0108, 0x02, false, Some(&[0x00, 0x02])).unwrap();
+    let mut a = HeaderBuilder::new()
+        .add_option(first.clone())
+        .add_option(second.clone())
+        .add_option(repeated.clone())
+        .build()
+        .unwrap();
+    let mut b = HeaderBuilder::new()
+        .add_option(second.clone())
+        .add_option(first.clone())
+        .add_option(repeated.clone())
+        .build()
+        .unwrap();
+    let (mut encoded_a, mut encoded_b): (Vec<u8>, Vec<u8>) = (vec![], vec![]);
+    a.marshal(&mut encoded_a).unwrap();
+    b.marshal(&mut encoded_b).unwrap();
+    assert_ne!(encoded_a, encoded_b);
+    a.canonicalize();
+    b.canonicalize();
+    encoded_a.clear();
+    encoded_b.clear();
+    a.marshal(&mut encoded_a).unwrap();
+    b.marshal(&mut encoded_b).unwrap();
+    assert_eq!(encoded_a, encoded_b);
+    // Equal keys keep their relative order
+    assert_eq!(a.options(), [second, first, repeated]);
+    let mut empty = Header::default();
+    empty.canonicalize();
+    assert!(empty.options.is_none());
+}
+
+#[test]
+fn geneve_header_normalize() {
+    let mut hdr = HeaderBuilder::new()
+        .protocol(0x6558)
+        .vni(0x000001)
+        .add_option(TunnelOption::new(0x0108, 0x01, false, None).unwrap())
+        .build()
+        .unwrap();
+    hdr.normalize();
+    assert!(!hdr.critical_flag);
+    hdr.options
+        .get_or_insert_with(Vec::new)
+        .push(TunnelOption::new(0x0108, 0x02, true, None).unwrap());
+    // Not enforced at marshal time
+    let mut buffer: Vec<u8> = vec![];
+    hdr.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer[1] & 0x40, 0x00);
+    hdr.normalize();
+    assert!(hdr.critical_flag);
+    buffer.clear();
+    hdr.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer[1] & 0x40, 0x40);
+}
+
+#[test]
+fn geneve_marshal_batch() {
+    let hdr = HeaderBuilder::new()
+        .protocol(0x6558)
+        .vni(0x000001)
+        .build()
+        .unwrap();
+    let with_option = HeaderBuilder::new()
+        .protocol(0x86dd)
+        .vni(0x00aaaaee)
+        .add_option(TunnelOption::new(0xffff, 0x0a, false, Some(&[0x00, 0x01])).unwrap())
+        .build()
+        .unwrap();
+    let packets = [
+        GenevePacket::from_parts(hdr.clone(), &[0xde, 0xad]),
+        GenevePacket::from_parts(with_option, &[0xbe, 0xef, 0x00]),
+        GenevePacket::from_parts(hdr, &[]),
+    ];
+    let mut out: Vec<u8> = vec![0x01];
+    let lens = marshal_batch(&packets, &mut out).unwrap();
+    assert_eq!(lens, [10, 19, 8]);
+    let mut expected: Vec<u8> = vec![0x01];
+    for i in packets.iter() {
+        i.marshal(&mut expected).unwrap();
     }
-}
\ No newline at end of file
+    assert_eq!(out, expected);
+    let overflowed = TunnelOption {
+        option_class: 0x0108,
+        option_type: 0x80,
+        c_flag: false,
+        data: None,
+    };
+    let bad = HeaderBuilder::new().add_option(overflowed).build().unwrap();
+    let packets = [packets[0].clone(), GenevePacket::from_parts(bad, &[])];
+    let mut out: Vec<u8> = vec![0x01];
+    assert!(matches!(
+        marshal_batch(&packets, &mut out),
+        Err(GeneveErr::InvalidOptionType)
+    ));
+    assert_eq!(out, [0x01]);
+}
@@ -1 +1,19 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 pub mod geneve;
+mod wire;
+
+#[cfg(feature = "tokio")]
+pub mod codec;
+
+#[cfg(feature = "pnet")]
+pub mod pnet;
+
+#[cfg(feature = "bytes")]
+mod bytes;
+
+#[cfg(feature = "arbitrary")]
+mod fuzz;
@@ -0,0 +1,44 @@
+use crate::geneve::{GeneveErr, GenevePacket};
+use pnet_packet::udp::UdpPacket;
+use pnet_packet::Packet;
+
+// pnet_packet has no Geneve type, the bridge is the payload of the carrying UDP datagram
+impl<'a> GenevePacket<'a> {
+    // Parses the payload of a UDP datagram, usually one sent to port 6081
+    pub fn from_pnet(udp: &'a UdpPacket<'_>) -> Result<Self, GeneveErr> {
+        GenevePacket::unmarshal(udp.payload())
+    }
+    // Encoded packet, ready for `MutableUdpPacket::set_payload`
+    pub fn to_pnet_bytes(&self) -> Result<Vec<u8>, GeneveErr> {
+        let mut buffer = Vec::with_capacity(self.total_len());
+        self.marshal(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+#[test]
+fn geneve_pnet_roundtrip() {
+    use pnet_packet::udp::MutableUdpPacket;
+
+    let encoded_payload: [u8; 30] = [
+        0x04, 0x00, 0x86, 0xdd, 0xaa, 0xaa, 0xee, 0x00, 0xff, 0xff, 0x0a, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0xff, 0xff, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00,
+    ];
+    let mut datagram = [0; 8 + 30];
+    let mut udp = MutableUdpPacket::new(&mut datagram).unwrap();
+    udp.set_destination(6081);
+    udp.set_length(38);
+    udp.set_payload(&encoded_payload);
+    let udp = UdpPacket::new(&datagram).unwrap();
+    let packet = GenevePacket::from_pnet(&udp).unwrap();
+    assert_eq!(packet.hdr.vni, 0x00aaaaee);
+    assert_eq!(packet.inner_payload(), [0xde, 0xad, 0xbe, 0xef, 0x00, 0x00]);
+    let bytes = packet.to_pnet_bytes().unwrap();
+    assert_eq!(bytes, encoded_payload);
+    let mut rebuilt = [0; 8 + 30];
+    let mut udp = MutableUdpPacket::new(&mut rebuilt).unwrap();
+    udp.set_destination(6081);
+    udp.set_length(38);
+    udp.set_payload(&bytes);
+    assert_eq!(rebuilt, datagram);
+}
@@ -0,0 +1,45 @@
+// Big-endian field access. Callers check the buffer length once up front, so the helpers
+// index directly and panic on an out-of-bounds position
+
+#[inline]
+pub(crate) fn read_u16_be(buffer: &[u8], pos: usize) -> u16 {
+    u16::from_be_bytes([buffer[pos], buffer[pos + 1]])
+}
+
+#[inline]
+pub(crate) fn read_u24_be(buffer: &[u8], pos: usize) -> u32 {
+    u32::from_be_bytes([0x00, buffer[pos], buffer[pos + 1], buffer[pos + 2]])
+}
+
+#[inline]
+pub(crate) fn write_u16_be(buffer: &mut [u8], pos: usize, value: u16) {
+    buffer[pos..pos + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+// Bits above 24 are dropped
+#[inline]
+pub(crate) fn write_u24_be(buffer: &mut [u8], pos: usize, value: u32) {
+    buffer[pos..pos + 3].copy_from_slice(&value.to_be_bytes()[1..]);
+}
+
+#[test]
+fn wire_u24_roundtrip() {
+    let mut buffer = [0xee; 5];
+    for value in [0x000000, 0x000001, 0x00aaaaee, 0x00ffffff] {
+        write_u24_be(&mut buffer, 1, value);
+        assert_eq!(buffer[0], 0xee);
+        assert_eq!(buffer[4], 0xee);
+        assert_eq!(read_u24_be(&buffer, 1), value);
+    }
+    write_u24_be(&mut buffer, 1, 0x01123456);
+    assert_eq!(buffer[1..4], [0x12, 0x34, 0x56]);
+    assert_eq!(read_u24_be(&buffer, 2), 0x3456ee);
+}
+
+#[test]
+fn wire_u16_roundtrip() {
+    let mut buffer = [0; 4];
+    write_u16_be(&mut buffer, 2, 0x86dd);
+    assert_eq!(buffer, [0x00, 0x00, 0x86, 0xdd]);
+    assert_eq!(read_u16_be(&buffer, 2), 0x86dd);
+}
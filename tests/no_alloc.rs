@@ -0,0 +1,84 @@
+// Only the allocation free API, run with `--no-default-features` to check the no_std build
+// can still parse and encode a header
+use geneve_rs::geneve::{FixedHeader, GeneveErr, OptionsIter, TunnelOption, MIN_GENEVE_HDR};
+
+const ENCODED: [u8; 28] = [
+    0x04, 0x40, 0x65, 0x58, 0x00, 0x00, 0x0b, 0x00, 0x01, 0x02, 0x80, 0x01, 0x00, 0x80, 0x00, 0x22,
+    0x01, 0x08, 0x03, 0x01, 0xc0, 0xff, 0xee, 0x01, 0xaa, 0xbb, 0xcc, 0xdd,
+];
+
+#[test]
+fn no_alloc_fixed_header_roundtrip() {
+    let (fixed, opts_end) = FixedHeader::unmarshal(&ENCODED).unwrap();
+    assert_eq!(opts_end, 24);
+    assert_eq!(fixed.protocol, 0x6558);
+    assert_eq!(fixed.vni, 0x00000b);
+    assert!(fixed.critical_flag);
+    let mut options = OptionsIter::new(&ENCODED).unwrap();
+    let opts: [TunnelOption; 2] = core::array::from_fn(|_| options.next().unwrap());
+    let mut buffer = [0; 24];
+    assert_eq!(fixed.marshal_to_slice(&opts, &mut buffer).unwrap(), 24);
+    assert_eq!(buffer, ENCODED[..24]);
+    assert_eq!(fixed.encode(16), ENCODED[..MIN_GENEVE_HDR]);
+}
+
+#[test]
+fn no_alloc_fixed_header_errors() {
+    assert!(matches!(
+        FixedHeader::unmarshal(&ENCODED[..4]),
+        Err(GeneveErr::BufferTooShort { needed: 8, got: 4 })
+    ));
+    assert!(matches!(
+        FixedHeader::unmarshal(&ENCODED[..20]),
+        Err(GeneveErr::BufferTooShort {
+            needed: 24,
+            got: 20
+        })
+    ));
+    let (fixed, _) = FixedHeader::unmarshal(&ENCODED).unwrap();
+    let opts = [TunnelOption::new(0x0102, 0x00, true, Some(&[0; 4])).unwrap()];
+    assert!(matches!(
+        fixed.marshal_to_slice(&opts, &mut [0; 12]),
+        Err(GeneveErr::OutputTooSmall {
+            needed: 16,
+            got: 12
+        })
+    ));
+}
+
+#[test]
+fn no_alloc_unmarshal_into() {
+    let empty = TunnelOption::new(0, 0, false, None).unwrap();
+    let mut opts: [TunnelOption; 3] = core::array::from_fn(|_| empty.clone());
+    let (fixed, cursor, count) = FixedHeader::unmarshal_into(&ENCODED, &mut opts).unwrap();
+    assert_eq!((cursor, count), (24, 2));
+    assert_eq!(fixed.protocol, 0x6558);
+    assert_eq!(opts[0].option_class, 0x0102);
+    assert!(opts[0].c_flag);
+    assert_eq!(opts[1].data_as_u32(), Some(0xc0ffee01));
+    assert_eq!(opts[2], empty);
+    let mut short: [TunnelOption; 1] = core::array::from_fn(|_| empty.clone());
+    assert!(matches!(
+        FixedHeader::unmarshal_into(&ENCODED, &mut short),
+        Err(GeneveErr::OptionSlotsTooFew { needed: 2, got: 1 })
+    ));
+}
+
+#[test]
+fn no_alloc_buffer_helpers() {
+    assert_eq!(FixedHeader::peek(&ENCODED).unwrap(), 24);
+    assert_eq!(FixedHeader::version_of(&ENCODED), Some(0));
+    assert_eq!(FixedHeader::raw_version(&[0xc0]), Some(3));
+    assert_eq!(FixedHeader::raw_version(&[]), None);
+    assert_eq!(FixedHeader::raw_options(&ENCODED), Some(&ENCODED[8..24]));
+    assert_eq!(FixedHeader::count_options(&ENCODED), Some(2));
+    assert_eq!(FixedHeader::count_options(&ENCODED[..20]), None);
+    let mut buffer = ENCODED;
+    FixedHeader::rewrite_vni_in_place(&mut buffer, 0x123456).unwrap();
+    assert_eq!(buffer[4..7], [0x12, 0x34, 0x56]);
+    assert_eq!(buffer[7..], ENCODED[7..]);
+    assert!(matches!(
+        FixedHeader::rewrite_vni_in_place(&mut buffer, 0x01000000),
+        Err(GeneveErr::InvalidVni)
+    ));
+}
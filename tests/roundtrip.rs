@@ -0,0 +1,89 @@
+#![cfg(feature = "alloc")]
+
+use geneve_rs::geneve::{
+    Header, HeaderBuilder, TunnelOption, MAX_DATA_SIZE, MAX_OPTIONS_LEN, MIN_OPT_SIZE,
+};
+use proptest::prelude::*;
+
+fn padded(len: usize) -> usize {
+    len.div_ceil(4) * 4
+}
+
+prop_compose! {
+    fn option_data()(
+        option_class in any::<u16>(),
+        option_type in 0..=0x7f_u8,
+        c_flag in any::<bool>(),
+        data in proptest::option::of(proptest::collection::vec(any::<u8>(), 1..=MAX_DATA_SIZE)),
+    ) -> (u16, u8, bool, Option<Vec<u8>>) {
+        (option_class, option_type, c_flag, data)
+    }
+}
+
+prop_compose! {
+    // Drops trailing options once the total would exceed the options length field
+    fn options_data()(options in proptest::collection::vec(option_data(), 0..=8))
+        -> Vec<(u16, u8, bool, Option<Vec<u8>>)> {
+        let mut total = 0;
+        options
+            .into_iter()
+            .take_while(|(_, _, _, data)| {
+                total += MIN_OPT_SIZE + padded(data.as_ref().map_or(0, |i| i.len()));
+                total <= MAX_OPTIONS_LEN
+            })
+            .collect()
+    }
+}
+
+proptest! {
+    #[test]
+    fn header_roundtrip(
+        control_flag in any::<bool>(),
+        critical_flag in any::<bool>(),
+        protocol in any::<u16>(),
+        vni in 0..=0x00ff_ffff_u32,
+        options in options_data(),
+    ) {
+        let mut builder = HeaderBuilder::new()
+            .control_flag(control_flag)
+            .critical_flag(critical_flag)
+            .protocol(protocol)
+            .vni(vni);
+        for (option_class, option_type, c_flag, data) in options.iter() {
+            builder = builder.add_option(
+                TunnelOption::new(*option_class, *option_type, *c_flag, data.as_deref()).unwrap(),
+            );
+        }
+        let hdr = builder.build().unwrap();
+        let mut buffer: Vec<u8> = vec![];
+        hdr.marshal(&mut buffer).unwrap();
+        prop_assert_eq!(buffer.len(), hdr.header_len());
+
+        let (decoded, cursor) = Header::unmarshal(&buffer).unwrap();
+        prop_assert_eq!(cursor, buffer.len());
+        prop_assert_eq!(decoded.version, 0);
+        prop_assert_eq!(decoded.control_flag, control_flag);
+        prop_assert_eq!(decoded.critical_flag, critical_flag);
+        prop_assert_eq!(decoded.protocol, protocol);
+        prop_assert_eq!(decoded.vni, vni);
+        let decoded_options = decoded.options.unwrap_or_default();
+        prop_assert_eq!(decoded_options.len(), options.len());
+        for (i, (option_class, option_type, c_flag, data)) in
+            decoded_options.iter().zip(options.iter())
+        {
+            prop_assert_eq!(i.option_class, *option_class);
+            prop_assert_eq!(i.option_type, *option_type);
+            prop_assert_eq!(i.c_flag, *c_flag);
+            // Decoded data carries the zero padding added on the wire
+            match (i.data, data) {
+                (Some(decoded), Some(data)) => {
+                    prop_assert_eq!(decoded.len(), padded(data.len()));
+                    prop_assert_eq!(&decoded[..data.len()], &data[..]);
+                    prop_assert!(decoded[data.len()..].iter().all(|k| *k == 0));
+                }
+                (None, None) => {}
+                (decoded, data) => prop_assert!(false, "{:?} != {:?}", decoded, data),
+            }
+        }
+    }
+}
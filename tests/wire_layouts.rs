@@ -0,0 +1,160 @@
+// Hand-built vectors following the header and option layouts described by the source cited
+// on each test, each parsed and then re-marshalled byte for byte. They are not packet
+// captures: VNIs, IDs, option data and inner frames are filler values
+#![cfg(feature = "alloc")]
+
+use geneve_rs::geneve::{
+    GeneveErr, GenevePacket, Header, HeaderBuilder, OptionClass, ProtocolType, TunnelOption,
+};
+
+// Parses `encoded` as a whole packet and checks it encodes back to the same bytes
+fn roundtrip(encoded: &[u8]) -> GenevePacket<'_> {
+    let packet = GenevePacket::unmarshal(encoded).unwrap();
+    packet.hdr.validate().unwrap();
+    let mut buffer: Vec<u8> = vec![];
+    packet.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded);
+    let mut slice = vec![0; encoded.len()];
+    assert_eq!(packet.marshal_to_slice(&mut slice).unwrap(), encoded.len());
+    assert_eq!(slice, encoded);
+    packet
+}
+
+// RFC 8926 section 3.4: version 0, no options, Protocol Type 0x6558 for an Ethernet frame
+#[test]
+fn layout_rfc8926_ethernet_no_options() {
+    let encoded: [u8; 22] = [
+        0x00, 0x00, 0x65, 0x58, 0x00, 0x00, 0x01, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02,
+        0x00, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06,
+    ];
+    let packet = roundtrip(&encoded);
+    assert_eq!(packet.hdr.vni, 1);
+    assert_eq!(packet.payload_offset(), 8);
+    assert_eq!(
+        packet.decapsulate(),
+        (ProtocolType::Ethernet, &encoded[8..])
+    );
+}
+
+// RFC 8926 section 3.4: O bit set for a control packet, carrying IPv6 with no options
+#[test]
+fn layout_rfc8926_oam_ipv6() {
+    let encoded: [u8; 12] = [
+        0x00, 0x80, 0x86, 0xdd, 0x12, 0x34, 0x56, 0x00, 0x60, 0x00, 0x00, 0x00,
+    ];
+    let packet = roundtrip(&encoded);
+    assert!(packet.is_oam());
+    assert!(!packet.hdr.has_critical_options());
+    assert_eq!(packet.hdr.vni, 0x123456);
+    assert_eq!(packet.hdr.protocol_type(), ProtocolType::Ipv6);
+}
+
+// Option layout from the AWS Gateway Load Balancer documentation, "Geneve TLV": class 0x0108
+// with type 1 the 8-byte GWLB endpoint ID, type 2 the 8-byte attachment ID and type 3 the
+// 4-byte flow cookie, wrapping an IPv4 packet. The IDs and cookie are made up
+#[test]
+fn layout_aws_gwlb() {
+    let encoded: [u8; 44] = [
+        0x08, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x08, 0x01, 0x02, 0x0a, 0x1b, 0x2c,
+        0x3d, 0x4e, 0x5f, 0x60, 0x71, 0x01, 0x08, 0x02, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x01, 0x08, 0x03, 0x01, 0xc0, 0xff, 0xee, 0x01, 0x45, 0x00, 0x00, 0x14,
+    ];
+    let packet = roundtrip(&encoded);
+    assert_eq!(packet.hdr.header_len(), 40);
+    assert_eq!(packet.hdr.protocol_type(), ProtocolType::Ipv4);
+    let options = packet.options();
+    assert_eq!(options.len(), 3);
+    assert!(options
+        .iter()
+        .all(|i| i.class() == OptionClass::Amazon(0x0108)));
+    assert_eq!(
+        packet.hdr.find_option(0x0108, 0x01).unwrap().data_as_u64(),
+        Some(0x0a1b2c3d4e5f6071)
+    );
+    assert_eq!(
+        packet.hdr.find_option(0x0108, 0x03).unwrap().data_as_u32(),
+        Some(0xc0ffee01)
+    );
+    assert_eq!(packet.inner_payload(), [0x45, 0x00, 0x00, 0x14]);
+}
+
+// Option layout from the OVN architecture documentation, "Tunnel Encapsulations": class
+// 0x0102, critical type 0x80 and 4 bytes holding the logical ingress and egress ports, VNI set
+// to the datapath key. Port and datapath numbers are made up
+#[test]
+fn layout_ovn_critical_option() {
+    let encoded: [u8; 20] = [
+        0x02, 0x40, 0x65, 0x58, 0x00, 0x00, 0x05, 0x00, 0x01, 0x02, 0x80, 0x01, 0x00, 0x03, 0x00,
+        0x07, 0x00, 0x00, 0x5e, 0x00,
+    ];
+    let packet = roundtrip(&encoded);
+    assert!(packet.hdr.has_critical_options());
+    let option = &packet.options()[0];
+    assert_eq!(option.class(), OptionClass::Ovn);
+    assert!(option.c_flag);
+    assert_eq!(option.option_type, 0x00);
+    assert!(matches!(
+        packet.validate_critical(&[]),
+        Err(GeneveErr::UnknownCriticalOption {
+            class: 0x0102,
+            option_type: 0x00
+        })
+    ));
+    assert!(packet.validate_critical(&[(0x0102, 0x00)]).is_ok());
+}
+
+// Options from the tc-tunnel_key(8) example `geneve_opts 0102:80:00800022:0102:80:00800022`,
+// two options given as class:type:data, with the type octet including the critical bit
+#[test]
+fn layout_tc_tunnel_key_geneve_opts() {
+    let encoded: [u8; 24] = [
+        0x04, 0x40, 0x65, 0x58, 0x00, 0x00, 0x0b, 0x00, 0x01, 0x02, 0x80, 0x01, 0x00, 0x80, 0x00,
+        0x22, 0x01, 0x02, 0x80, 0x01, 0x00, 0x80, 0x00, 0x22,
+    ];
+    let packet = roundtrip(&encoded);
+    assert_eq!(packet.options().len(), 2);
+    assert_eq!(packet.options()[0], packet.options()[1]);
+    assert_eq!(packet.options()[0].data_as_u32(), Some(0x00800022));
+    assert!(packet.inner_payload().is_empty());
+}
+
+// Class 0x0104 is assigned to VMware in the IANA Geneve Option Class registry. NSX option
+// types are not published, so only the class is taken from a source, type and data are filler
+#[test]
+fn layout_vmware_option_class() {
+    let encoded: [u8; 24] = [
+        0x03, 0x00, 0x65, 0x58, 0x00, 0x10, 0x00, 0x00, 0x01, 0x04, 0x01, 0x02, 0x00, 0x00, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x02, 0xaa, 0xbb, 0xcc, 0xdd,
+    ];
+    let packet = roundtrip(&encoded);
+    assert_eq!(packet.options()[0].class(), OptionClass::Vmware);
+    assert_eq!(packet.options()[0].data_len(), 8);
+    assert_eq!(packet.inner_payload(), [0xaa, 0xbb, 0xcc, 0xdd]);
+}
+
+// RFC 8926 section 3.5: option data is a multiple of 4 bytes on the wire. Data of other
+// lengths is zero padded when encoding and comes back padded, so it compares semantically
+#[test]
+fn layout_rfc8926_unaligned_option_data() {
+    let hdr = HeaderBuilder::new()
+        .protocol(0x6558)
+        .vni(0x000102)
+        .add_option(TunnelOption::new(0x0108, 0x03, false, Some(&[0x01, 0x02, 0x03])).unwrap())
+        .build()
+        .unwrap();
+    let encoded: [u8; 16] = [
+        0x02, 0x00, 0x65, 0x58, 0x00, 0x01, 0x02, 0x00, 0x01, 0x08, 0x03, 0x01, 0x01, 0x02, 0x03,
+        0x00,
+    ];
+    let mut buffer: Vec<u8> = vec![];
+    hdr.marshal(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded);
+    let packet = roundtrip(&encoded);
+    assert!(packet.options()[0].eq_semantic(&hdr.options()[0]));
+    assert_eq!(
+        packet.options()[0].data_without_trailing_zeros(),
+        Some(&[0x01, 0x02, 0x03][..])
+    );
+    let (parsed, _) = Header::unmarshal(&buffer).unwrap();
+    assert_eq!(parsed.options()[0].data_words(), 1);
+}